@@ -0,0 +1,55 @@
+//! Turns `data/matrix.json` into dense tables compiled straight into the binary, so that the app
+//! neither ships nor parses the JSON. See `destinations::travel` for the lookup.
+
+use std::{collections::BTreeMap, env, fmt::Write, fs, path::Path};
+
+use serde_json::Value;
+
+const MATRIX: &str = "data/matrix.json";
+/// Marks a pair without a route in both tables.
+const MISSING: u32 = u32::MAX;
+
+type Matrix = BTreeMap<String, BTreeMap<String, Vec<Value>>>;
+
+/// Metres and seconds from the first element that has both.
+fn leg(elements: &[Value]) -> Option<(u32, u32)> {
+    elements.iter().find_map(|e| {
+        let metres = e.pointer("/distance/value")?.as_u64()?;
+        let seconds = e.pointer("/duration/value")?.as_u64()?;
+        Some((metres.try_into().ok()?, seconds.try_into().ok()?))
+    })
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={MATRIX}");
+    let json = fs::read_to_string(MATRIX).expect("data/matrix.json is missing");
+    let matrix: Matrix = serde_json::from_str(&json).expect("data/matrix.json is not a matrix");
+
+    let mut names: Vec<&String> = matrix
+        .keys()
+        .chain(matrix.values().flat_map(|r| r.keys()))
+        .collect();
+    names.sort();
+    names.dedup();
+    let index = |name: &String| names.binary_search(&name).unwrap();
+    let n = names.len();
+    let mut distances = vec![MISSING; n * n];
+    let mut durations = vec![MISSING; n * n];
+    for (from, row) in &matrix {
+        for (to, elements) in row {
+            if let Some((metres, seconds)) = leg(elements) {
+                let cell = index(from) * n + index(to);
+                distances[cell] = metres;
+                durations[cell] = seconds;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    _ = writeln!(out, "const MISSING: u32 = {MISSING};");
+    _ = writeln!(out, "static NAMES: [&str; {n}] = {names:?};");
+    _ = writeln!(out, "static DISTANCES: [u32; {}] = {distances:?};", n * n);
+    _ = writeln!(out, "static DURATIONS: [u32; {}] = {durations:?};", n * n);
+    let target = Path::new(&env::var("OUT_DIR").unwrap()).join("matrix.rs");
+    fs::write(target, out).unwrap();
+}
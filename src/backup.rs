@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    destinations::CustomTrips, killring::Killring, settings::Settings, storage, Trip, Trips,
+};
+
+/// Bump when the envelope changes. The trips inside carry their own schema version.
+pub const BACKUP_VERSION: u64 = 1;
+
+/// Everything the app keeps in the browser, in one file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Backup {
+    version: u64,
+    created: NaiveDateTime,
+    /// The `my-trips` document exactly as `storage` writes it, so it goes through the migrations.
+    trips: Value,
+    custom_trips: CustomTrips,
+    settings: Settings,
+    /// Left out by backups made before the trash was included.
+    #[serde(default)]
+    trash: Killring,
+}
+
+impl Backup {
+    pub fn new(
+        trips: &Trips,
+        custom_trips: CustomTrips,
+        settings: Settings,
+        trash: Killring,
+    ) -> Result<Self, String> {
+        let trips = storage::save(trips).map_err(|e| e.to_string())?;
+        Ok(Self {
+            version: BACKUP_VERSION,
+            created: Local::now().naive_local(),
+            trips: serde_json::from_str(&trips).map_err(|e| e.to_string())?,
+            custom_trips,
+            settings,
+            trash,
+        })
+    }
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+    pub fn filename(&self) -> String {
+        format!("loggbok-{}.json", self.created.format("%Y-%m-%d"))
+    }
+}
+
+/// A backup that has been read and understood, ready to be previewed against what we have.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Restore {
+    pub created: NaiveDateTime,
+    pub trips: Trips,
+    pub custom_trips: CustomTrips,
+    pub settings: Settings,
+    pub trash: Killring,
+}
+
+impl Restore {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let backup: Backup =
+            serde_json::from_str(raw).map_err(|e| format!("Filen kunde inte läsas: {e}"))?;
+        if backup.version > BACKUP_VERSION {
+            return Err(format!(
+                "Säkerhetskopian är gjord med en nyare version ({}) av appen",
+                backup.version
+            ));
+        }
+        let trips = storage::load(&backup.trips.to_string()).map_err(|e| e.to_string())?;
+        Ok(Self {
+            created: backup.created,
+            trips,
+            custom_trips: backup.custom_trips,
+            settings: backup.settings,
+            trash: backup.trash,
+        })
+    }
+
+    pub fn preview(&self, current: &Trips) -> Preview {
+        let by_uuid: HashMap<Uuid, &Trip> = current.trips.iter().map(|t| (t.uuid, t)).collect();
+        let mut preview = Preview::default();
+        for trip in &self.trips.trips {
+            match by_uuid.get(&trip.uuid) {
+                Some(&ours) if ours == trip => preview.unchanged += 1,
+                Some(&ours) => preview.changed.push((ours.clone(), trip.clone())),
+                None => match current.trips.iter().find(|t| t.same_journey(trip)) {
+                    Some(ours) => preview.conflicting.push((ours.clone(), trip.clone())),
+                    None => preview.new.push(trip.clone()),
+                },
+            }
+        }
+        preview
+    }
+
+    /// Adds new trips and takes the backup's version of changed ones. Conflicts and settings
+    /// stay as they are here. Vehicles and cost centres the trips refer to come along, and so
+    /// does the trash.
+    pub fn merge_into(
+        self,
+        trips: &mut Trips,
+        custom_trips: &mut CustomTrips,
+        trash: &mut Killring,
+    ) {
+        let preview = self.preview(trips);
+        trips.vehicles.merge(self.trips.vehicles);
+        trips.cost_centres.merge(self.trips.cost_centres);
+        for (_, theirs) in preview.changed {
+            trips.update(theirs);
+        }
+        trips.merge(preview.new);
+        custom_trips.merge(self.custom_trips);
+        trash.merge(self.trash, trips);
+    }
+}
+
+/// What restoring a backup would do, trip by trip.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preview {
+    /// Only in the backup.
+    pub new: Vec<Trip>,
+    /// Same trip in both, edited on one side. Ours first.
+    pub changed: Vec<(Trip, Trip)>,
+    /// Different trips for the same date and route, probably entered twice. Ours first.
+    pub conflicting: Vec<(Trip, Trip)>,
+    pub unchanged: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::test_trip;
+
+    fn at(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    fn only(trips: Vec<Trip>) -> Trips {
+        Trips {
+            trips,
+            ..Default::default()
+        }
+    }
+
+    fn restore(trips: &Trips, trash: Killring) -> Restore {
+        let settings = Settings {
+            employee: "Kim".into(),
+            ..Default::default()
+        };
+        let backup = Backup::new(trips, CustomTrips::default(), settings, trash).unwrap();
+        Restore::parse(&backup.to_json().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn a_backup_reads_back_as_it_was_made() {
+        let trips = only(vec![test_trip(), test_trip()]);
+        let mut trash = Killring::default();
+        trash.add(test_trip(), at(2));
+        let restored = restore(&trips, trash.clone());
+        assert_eq!(restored.trips, trips);
+        assert_eq!(restored.settings.employee, "Kim");
+        assert_eq!(restored.trash, trash);
+    }
+
+    #[test]
+    fn merging_adds_new_trips_and_takes_edits_once_by_uuid() {
+        let kept = test_trip();
+        let edited = test_trip();
+        let theirs = only(vec![
+            kept.clone(),
+            Trip {
+                reason: "Kurs".into(),
+                ..edited.clone()
+            },
+            Trip {
+                date: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+                ..test_trip()
+            },
+        ]);
+        let backup = restore(&theirs, Killring::default());
+        let mut ours = only(vec![kept, edited]);
+
+        let preview = backup.preview(&ours);
+        assert_eq!(preview.unchanged, 1);
+        assert_eq!(preview.changed.len(), 1);
+        assert_eq!(preview.new.len(), 1);
+
+        let (mut custom, mut trash) = (CustomTrips::default(), Killring::default());
+        backup
+            .clone()
+            .merge_into(&mut ours, &mut custom, &mut trash);
+        assert_eq!(ours.trips.len(), 3);
+        assert_eq!(ours.trips.iter().filter(|t| t.reason == "Kurs").count(), 1);
+        let once = ours.clone();
+        backup.merge_into(&mut ours, &mut custom, &mut trash);
+        assert_eq!(ours, once);
+    }
+
+    #[test]
+    fn merging_brings_the_trash_but_not_trips_we_still_have() {
+        let live = test_trip();
+        let gone = test_trip();
+        let mut theirs = Killring::default();
+        theirs.add(live.clone(), at(3));
+        theirs.add(gone.clone(), at(4));
+        let backup = restore(&Trips::default(), theirs);
+
+        let mut ours = only(vec![live]);
+        let mut trash = Killring::default();
+        backup.merge_into(&mut ours, &mut CustomTrips::default(), &mut trash);
+        assert_eq!(trash.all().len(), 1);
+        assert_eq!(trash.all()[0].trip, gone);
+    }
+
+    #[test]
+    fn refuses_backups_from_a_newer_version() {
+        let backup = Backup::new(
+            &Trips::default(),
+            CustomTrips::default(),
+            Settings::default(),
+            Killring::default(),
+        )
+        .unwrap();
+        let mut json = serde_json::to_value(&backup).unwrap();
+        json["version"] = (BACKUP_VERSION + 1).into();
+        let error = Restore::parse(&json.to_string()).unwrap_err();
+        assert!(error.contains("nyare version"));
+    }
+
+    #[test]
+    fn reads_backups_made_before_the_trash_was_included() {
+        let backup = Backup::new(
+            &only(vec![test_trip()]),
+            CustomTrips::default(),
+            Settings::default(),
+            Killring::default(),
+        )
+        .unwrap();
+        let mut json = serde_json::to_value(&backup).unwrap();
+        json.as_object_mut().unwrap().remove("trash");
+        let restored = Restore::parse(&json.to_string()).unwrap();
+        assert_eq!(restored.trips.trips.len(), 1);
+        assert!(restored.trash.all().is_empty());
+    }
+}
@@ -0,0 +1,425 @@
+//! Fills `data/matrix.json` with driving distances and times between the places in
+//! `destinations.txt`.
+//!
+//! Only pairs missing from the matrix are asked for, and the file is saved after every origin,
+//! so running it again after adding a destination or after a failure picks up where it stopped.
+//!
+//! `cargo run --features prepare --bin prepare -- google` asks Google's distance matrix and needs
+//! `GMAPS_TOKEN`. `cargo run --features prepare --bin prepare -- osrm` asks an OSRM server at
+//! `OSRM_URL` (default `http://localhost:5000`) and skips the places missing from
+//! `data/coordinates.json`.
+//!
+//! `cargo run --features prepare --bin prepare -- coordinates` fills that file through Google's
+//! geocoder, also with `GMAPS_TOKEN`. The app ships it to estimate pairs missing from the matrix.
+
+use std::{collections::BTreeMap, env, fs, time::Duration};
+
+use google_maps::prelude::*;
+use indicatif::ProgressIterator;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const DESTINATIONS: &str = "destinations.txt";
+const MATRIX: &str = "data/matrix.json";
+const COORDINATES: &str = "data/coordinates.json";
+/// Google refuses distance matrix requests with more destinations than this.
+const GOOGLE_MAX_DESTINATIONS: usize = 25;
+/// Appended to each place when geocoding by name.
+const REGION: &str = "Motala kommun";
+
+type Matrix = BTreeMap<String, BTreeMap<String, Vec<Value>>>;
+type Places = BTreeMap<String, Coordinates>;
+
+/// Metres and seconds of driving from one place to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Leg {
+    metres: u32,
+    seconds: u32,
+}
+
+impl Leg {
+    /// Shaped like Google's elements, which is what the app reads.
+    fn to_json(self) -> Value {
+        json!({
+            "distance": {
+                "text": format!("{:.1} km", self.metres as f32 / 1000.).replace('.', ","),
+                "value": self.metres,
+            },
+            "duration": {
+                "text": format!("{} min", (self.seconds + 30) / 60),
+                "value": self.seconds,
+            },
+        })
+    }
+}
+
+trait RoutingProvider {
+    /// How many destinations `legs` may be asked for at once.
+    const MAX_DESTINATIONS: usize = usize::MAX;
+    /// Why `place` cannot be routed at all, if so.
+    fn skips(&self, _place: &str) -> Option<String> {
+        None
+    }
+    /// Legs from `origin` to each of `destinations`, `None` where no route was found.
+    async fn legs(&self, origin: &str, destinations: &[&str]) -> Result<Vec<Option<Leg>>, String>;
+}
+
+struct Google {
+    client: GoogleMapsClient,
+}
+
+impl Google {
+    fn new() -> Result<Self, String> {
+        let token = env::var("GMAPS_TOKEN").map_err(|_| "GMAPS_TOKEN saknas".to_owned())?;
+        let mut client = GoogleMapsClient::try_new(token).map_err(|e| e.to_string())?;
+        client.with_rate(&Api::All, 1, Duration::from_secs(1));
+        Ok(Self { client })
+    }
+    async fn locate(&self, place: &str) -> Result<Coordinates, String> {
+        let response = self
+            .client
+            .geocoding()
+            .with_address(format!("{place}, {REGION}"))
+            .execute()
+            .await
+            .map_err(|e| e.to_string())?;
+        let found = response.results.into_iter().next().ok_or("hittades inte")?;
+        let degrees = |d: Decimal| f64::try_from(d).map_err(|e| e.to_string());
+        Ok(Coordinates {
+            lat: degrees(found.geometry.location.lat)?,
+            lon: degrees(found.geometry.location.lng)?,
+        })
+    }
+}
+
+impl RoutingProvider for Google {
+    const MAX_DESTINATIONS: usize = GOOGLE_MAX_DESTINATIONS;
+
+    async fn legs(&self, origin: &str, destinations: &[&str]) -> Result<Vec<Option<Leg>>, String> {
+        let waypoint = |place: &str| Waypoint::Address(format!("{place}, {REGION}"));
+        let response = self
+            .client
+            .distance_matrix(
+                vec![waypoint(origin)],
+                destinations.iter().map(|d| waypoint(d)).collect(),
+            )
+            .with_language(Language::Swedish)
+            .with_restriction(Avoid::Tolls)
+            .execute()
+            .await
+            .map_err(|e| e.to_string())?;
+        let row = response.rows.into_iter().next().ok_or("tomt svar")?;
+        Ok(row
+            .elements
+            .into_iter()
+            .map(|element| {
+                Some(Leg {
+                    metres: element.distance?.value,
+                    seconds: element.duration?.value.num_seconds() as u32,
+                })
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Coordinates {
+    lat: f64,
+    lon: f64,
+}
+
+/// Any server speaking the OSRM HTTP API, like a local `osrm-routed`.
+struct Osrm {
+    base: String,
+    coordinates: Places,
+    http: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsrmTable {
+    code: String,
+    distances: Option<Vec<Vec<Option<f64>>>>,
+    durations: Option<Vec<Vec<Option<f64>>>>,
+}
+
+impl Osrm {
+    fn new() -> Result<Self, String> {
+        Ok(Self {
+            base: env::var("OSRM_URL").unwrap_or_else(|_| "http://localhost:5000".into()),
+            coordinates: read(COORDINATES)?,
+            http: reqwest::Client::new(),
+        })
+    }
+    fn locate(&self, place: &str) -> Result<String, String> {
+        let c = self
+            .coordinates
+            .get(place)
+            .ok_or_else(|| format!("{place} saknas i {COORDINATES}"))?;
+        Ok(format!("{},{}", c.lon, c.lat))
+    }
+}
+
+impl RoutingProvider for Osrm {
+    fn skips(&self, place: &str) -> Option<String> {
+        (!self.coordinates.contains_key(place)).then(|| format!("{place} saknas i {COORDINATES}"))
+    }
+
+    async fn legs(&self, origin: &str, destinations: &[&str]) -> Result<Vec<Option<Leg>>, String> {
+        let places = std::iter::once(&origin)
+            .chain(destinations)
+            .map(|p| self.locate(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        let url = format!(
+            "{}/table/v1/driving/{}?sources=0&annotations=distance,duration",
+            self.base.trim_end_matches('/'),
+            places.join(";")
+        );
+        let table: OsrmTable = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+        if table.code != "Ok" {
+            return Err(format!("OSRM svarade {}", table.code));
+        }
+        let first = |rows: Option<Vec<Vec<Option<f64>>>>| rows.and_then(|r| r.into_iter().next());
+        let (Some(distances), Some(durations)) = (first(table.distances), first(table.durations))
+        else {
+            return Err("OSRM svarade utan tabell".into());
+        };
+        // The first column is the origin itself.
+        Ok(distances
+            .into_iter()
+            .zip(durations)
+            .skip(1)
+            .map(|(metres, seconds)| {
+                Some(Leg {
+                    metres: metres?.round() as u32,
+                    seconds: seconds?.round() as u32,
+                })
+            })
+            .collect())
+    }
+}
+
+/// The JSON in `path`, or nothing yet if there is no such file.
+fn read<T: Default + serde::de::DeserializeOwned>(path: &str) -> Result<T, String> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| format!("{path}: {e}")),
+        Err(_) => Ok(T::default()),
+    }
+}
+
+/// Writes through a temporary file, so that an interrupted run never leaves half a file.
+fn save(path: &str, value: &impl Serialize) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    let temporary = format!("{path}.tmp");
+    fs::write(&temporary, json)?;
+    fs::rename(temporary, path)
+}
+
+/// Asks `provider` for the pairs among `places` missing from `matrix`, saving it to `path` after
+/// every origin.
+async fn fill<P: RoutingProvider>(provider: &P, places: &[&str], matrix: &mut Matrix, path: &str) {
+    let places: Vec<&str> = places
+        .iter()
+        .filter(|p| match provider.skips(p) {
+            Some(reason) => {
+                eprintln!("Hoppar över {reason}");
+                false
+            }
+            None => true,
+        })
+        .copied()
+        .collect();
+    let mut failed = 0;
+    for origin in places.iter().progress() {
+        let known = matrix.get(*origin);
+        let missing: Vec<&str> = places
+            .iter()
+            .filter(|d| *d != origin && known.is_none_or(|k| !k.contains_key(**d)))
+            .copied()
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        for chunk in missing.chunks(P::MAX_DESTINATIONS) {
+            match provider.legs(origin, chunk).await {
+                Ok(legs) => {
+                    let row = matrix.entry(origin.to_string()).or_default();
+                    for (destination, leg) in chunk.iter().zip(legs) {
+                        match leg {
+                            Some(leg) => {
+                                row.insert(destination.to_string(), vec![leg.to_json()]);
+                            }
+                            None => {
+                                failed += 1;
+                                eprintln!("Ingen väg från {origin} till {destination}");
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    failed += chunk.len();
+                    eprintln!("Från {origin}: {e}");
+                }
+            }
+        }
+        if let Err(e) = save(path, matrix) {
+            eprintln!("Kunde inte spara {path}: {e}");
+            return;
+        }
+    }
+    if failed > 0 {
+        eprintln!("{failed} par saknas fortfarande, kör igen för att försöka på nytt");
+    }
+}
+
+/// Geocodes the places missing from `places`, saving after each one like `fill`.
+async fn locate(google: &Google, wanted: &[&str], places: &mut Places) {
+    let missing: Vec<&str> = wanted
+        .iter()
+        .filter(|p| !places.contains_key(**p))
+        .copied()
+        .collect();
+    let mut failed = 0;
+    for place in missing.into_iter().progress() {
+        match google.locate(place).await {
+            Ok(coordinates) => {
+                places.insert(place.to_owned(), coordinates);
+                if let Err(e) = save(COORDINATES, places) {
+                    eprintln!("Kunde inte spara {COORDINATES}: {e}");
+                    return;
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("{place}: {e}");
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("{failed} platser saknas fortfarande, kör igen för att försöka på nytt");
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    let destinations = fs::read_to_string(DESTINATIONS).map_err(|e| e.to_string())?;
+    let places: Vec<&str> = destinations
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let mut matrix: Matrix = read(MATRIX)?;
+    match env::args().nth(1).as_deref() {
+        Some("google") => fill(&Google::new()?, &places, &mut matrix, MATRIX).await,
+        Some("osrm") => fill(&Osrm::new()?, &places, &mut matrix, MATRIX).await,
+        Some("coordinates") => locate(&Google::new()?, &places, &mut read(COORDINATES)?).await,
+        _ => return Err("Ange google, osrm eller coordinates".into()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    /// Answers every request with the same leg, remembering what it was asked.
+    #[derive(Default)]
+    struct Fake {
+        unplaced: Vec<&'static str>,
+        asked: RefCell<Vec<(String, Vec<String>)>>,
+    }
+
+    impl RoutingProvider for Fake {
+        const MAX_DESTINATIONS: usize = GOOGLE_MAX_DESTINATIONS;
+
+        fn skips(&self, place: &str) -> Option<String> {
+            self.unplaced.contains(&place).then(|| place.to_owned())
+        }
+        async fn legs(
+            &self,
+            origin: &str,
+            destinations: &[&str],
+        ) -> Result<Vec<Option<Leg>>, String> {
+            let asked = destinations.iter().map(|d| d.to_string()).collect();
+            self.asked.borrow_mut().push((origin.to_owned(), asked));
+            let leg = Leg {
+                metres: 1000,
+                seconds: 60,
+            };
+            Ok(vec![Some(leg); destinations.len()])
+        }
+    }
+
+    fn scratch(name: &str) -> String {
+        let path = env::temp_dir().join(format!("prepare-{name}-{}.json", std::process::id()));
+        path.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn asks_only_for_unknown_pairs_between_routable_places() {
+        let fake = Fake {
+            unplaced: vec!["D"],
+            ..Default::default()
+        };
+        let mut matrix = Matrix::new();
+        let known = Leg {
+            metres: 5,
+            seconds: 5,
+        };
+        matrix
+            .entry("A".into())
+            .or_default()
+            .insert("B".into(), vec![known.to_json()]);
+        let path = scratch("known");
+        fill(&fake, &["A", "B", "C", "D"], &mut matrix, &path).await;
+
+        let asked = fake.asked.take();
+        let asked: Vec<(&str, Vec<&str>)> = asked
+            .iter()
+            .map(|(o, d)| (o.as_str(), d.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(
+            asked,
+            [
+                ("A", vec!["C"]),
+                ("B", vec!["A", "C"]),
+                ("C", vec!["A", "B"])
+            ]
+        );
+        assert_eq!(matrix["A"]["B"], vec![known.to_json()]);
+        assert!(!matrix.contains_key("D"));
+        let saved: Matrix = read(&path).unwrap();
+        assert_eq!(saved, matrix);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn asks_for_at_most_25_destinations_at_a_time() {
+        let fake = Fake::default();
+        let names: Vec<String> = (0..30).map(|i| format!("Plats {i}")).collect();
+        let places: Vec<&str> = names.iter().map(String::as_str).collect();
+        let mut matrix = Matrix::new();
+        let path = scratch("chunks");
+        fill(&fake, &places, &mut matrix, &path).await;
+
+        let asked = fake.asked.take();
+        assert_eq!(asked.len(), 60);
+        assert!(asked
+            .iter()
+            .all(|(_, d)| d.len() <= GOOGLE_MAX_DESTINATIONS));
+        assert_eq!(asked[0].1.len(), 25);
+        assert_eq!(asked[1].1.len(), 4);
+        assert_eq!(matrix.values().map(BTreeMap::len).sum::<usize>(), 30 * 29);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+//! Optional sync backend: keeps every stamped trip field in SQLite and hands out what changed.
+//!
+//! Runs entirely locally with `cargo run --features server --bin sync-server`. Configured through
+//! `SYNC_ADDR` (default `127.0.0.1:8787`), `SYNC_DB` (default `sync.sqlite`) and, to require
+//! `Authorization: Bearer <token>` from clients, `SYNC_TOKEN`.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use servis::sync::{Field, Pull, Push, Record, Stamp};
+use tower_http::cors::CorsLayer;
+use uuid::Uuid;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS fields (
+        uuid TEXT NOT NULL,
+        field TEXT NOT NULL,
+        value TEXT NOT NULL,
+        at INTEGER NOT NULL,
+        device TEXT NOT NULL,
+        seq INTEGER NOT NULL,
+        PRIMARY KEY (uuid, field)
+    );
+    CREATE INDEX IF NOT EXISTS fields_seq ON fields (seq);
+";
+
+#[derive(Clone)]
+struct Server {
+    db: Arc<Mutex<Connection>>,
+    token: Option<String>,
+}
+
+type Failure = (StatusCode, String);
+
+fn internal(e: impl ToString) -> Failure {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// Writes each field that is newer than what is stored, numbering the writes in order.
+fn store(conn: &mut Connection, changes: Vec<Record>) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    let mut seq: u64 =
+        tx.query_row("SELECT COALESCE(MAX(seq), 0) FROM fields", [], |r| r.get(0))?;
+    for record in changes {
+        let uuid = record.uuid.to_string();
+        for (name, field) in record.fields {
+            let stored: Option<(i64, String)> = tx
+                .query_row(
+                    "SELECT at, device FROM fields WHERE uuid = ?1 AND field = ?2",
+                    params![uuid, name],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )
+                .optional()?;
+            let newer = stored.is_none_or(|(at, device)| {
+                let device = Uuid::parse_str(&device).unwrap_or_default();
+                field.stamp > Stamp { at, device }
+            });
+            if !newer {
+                continue;
+            }
+            seq += 1;
+            tx.execute(
+                "INSERT INTO fields (uuid, field, value, at, device, seq)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (uuid, field) DO UPDATE SET
+                    value = excluded.value, at = excluded.at,
+                    device = excluded.device, seq = excluded.seq",
+                params![
+                    uuid,
+                    name,
+                    field.value.to_string(),
+                    field.stamp.at,
+                    field.stamp.device.to_string(),
+                    seq
+                ],
+            )?;
+        }
+    }
+    tx.commit()
+}
+
+fn changes_since(conn: &Connection, since: u64) -> rusqlite::Result<Pull> {
+    let mut statement = conn.prepare(
+        "SELECT uuid, field, value, at, device, seq FROM fields WHERE seq > ?1 ORDER BY seq",
+    )?;
+    let mut records: BTreeMap<Uuid, Record> = BTreeMap::new();
+    let mut cursor = since;
+    let rows = statement.query_map(params![since], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+            r.get::<_, i64>(3)?,
+            r.get::<_, String>(4)?,
+            r.get::<_, u64>(5)?,
+        ))
+    })?;
+    for row in rows {
+        let (uuid, name, value, at, device, seq) = row?;
+        let (Ok(uuid), Ok(value)) = (Uuid::parse_str(&uuid), serde_json::from_str(&value)) else {
+            continue;
+        };
+        let stamp = Stamp {
+            at,
+            device: Uuid::parse_str(&device).unwrap_or_default(),
+        };
+        records
+            .entry(uuid)
+            .or_insert_with(|| Record::new(uuid))
+            .fields
+            .insert(name, Field { value, stamp });
+        cursor = cursor.max(seq);
+    }
+    Ok(Pull {
+        cursor,
+        changes: records.into_values().collect(),
+    })
+}
+
+async fn sync(
+    State(server): State<Server>,
+    headers: HeaderMap,
+    Json(push): Json<Push>,
+) -> Result<Json<Pull>, Failure> {
+    if let Some(token) = &server.token {
+        let sent = headers
+            .get("authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "));
+        if sent != Some(token.as_str()) {
+            return Err((StatusCode::UNAUTHORIZED, "fel nyckel".into()));
+        }
+    }
+    let mut conn = server.db.lock().map_err(internal)?;
+    store(&mut conn, push.changes).map_err(internal)?;
+    changes_since(&conn, push.since).map(Json).map_err(internal)
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = env::var("SYNC_ADDR").unwrap_or_else(|_| "127.0.0.1:8787".into());
+    let path = env::var("SYNC_DB").unwrap_or_else(|_| "sync.sqlite".into());
+    let conn = Connection::open(&path).expect("could not open database");
+    conn.execute_batch(SCHEMA).expect("could not create tables");
+    let server = Server {
+        db: Arc::new(Mutex::new(conn)),
+        token: env::var("SYNC_TOKEN").ok().filter(|t| !t.is_empty()),
+    };
+    // The app is served from elsewhere, so any origin may call.
+    let app = Router::new()
+        .route("/sync", post(sync))
+        .layer(CorsLayer::permissive())
+        .with_state(server);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .expect("could not bind address");
+    println!("Synkar på http://{addr} med {path}");
+    axum::serve(listener, app).await.expect("server stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn connection() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn
+    }
+
+    fn change(uuid: u128, name: &str, value: serde_json::Value, at: i64) -> Record {
+        let mut record = Record::new(Uuid::from_u128(uuid));
+        let stamp = Stamp {
+            at,
+            device: Uuid::from_u128(1),
+        };
+        record.fields.insert(name.into(), Field { value, stamp });
+        record
+    }
+
+    #[test]
+    fn hands_out_what_was_stored_after_the_cursor() {
+        let mut conn = connection();
+        store(&mut conn, vec![change(1, "reason", json!("Möte"), 10)]).unwrap();
+        let first = changes_since(&conn, 0).unwrap();
+        assert_eq!(first.cursor, 1);
+        assert_eq!(first.changes, vec![change(1, "reason", json!("Möte"), 10)]);
+
+        store(&mut conn, vec![change(2, "time", json!(7), 20)]).unwrap();
+        let second = changes_since(&conn, first.cursor).unwrap();
+        assert_eq!(second.cursor, 2);
+        assert_eq!(second.changes, vec![change(2, "time", json!(7), 20)]);
+        assert!(changes_since(&conn, second.cursor)
+            .unwrap()
+            .changes
+            .is_empty());
+    }
+
+    #[test]
+    fn keeps_the_newer_field() {
+        let mut conn = connection();
+        store(&mut conn, vec![change(1, "reason", json!("Kurs"), 20)]).unwrap();
+        store(&mut conn, vec![change(1, "reason", json!("Möte"), 10)]).unwrap();
+        let pull = changes_since(&conn, 0).unwrap();
+        assert_eq!(pull.cursor, 1);
+        assert_eq!(pull.changes, vec![change(1, "reason", json!("Kurs"), 20)]);
+
+        store(&mut conn, vec![change(1, "reason", json!("Annat"), 30)]).unwrap();
+        let pull = changes_since(&conn, 1).unwrap();
+        assert_eq!(pull.changes, vec![change(1, "reason", json!("Annat"), 30)]);
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// Why a trip was made, which decides how Skatteverket sees it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Tjänsteresa, the only kind that is reported and gives milersättning.
+    #[default]
+    Business,
+    Private,
+    /// Between home and the usual place of work, which is not a tjänsteresa.
+    Commute,
+}
+
+impl Category {
+    pub const ALL: [Category; 3] = [Self::Business, Self::Private, Self::Commute];
+
+    pub fn for_humans(&self) -> &'static str {
+        match self {
+            Self::Business => "Tjänsteresa",
+            Self::Private => "Privat",
+            Self::Commute => "Pendling",
+        }
+    }
+    /// Reads the name shown to humans, in any case.
+    pub fn from_human(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|c| c.for_humans().to_lowercase() == name)
+    }
+    pub fn is_business(&self) -> bool {
+        *self == Self::Business
+    }
+}
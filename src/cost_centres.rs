@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Trip;
+
+/// A verksamhet or ansvar that travel is billed to, known by the code finance uses.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CostCentre {
+    pub code: String,
+    pub name: String,
+}
+
+impl CostCentre {
+    pub fn for_humans(&self) -> String {
+        if self.name.is_empty() {
+            self.code.clone()
+        } else {
+            format!("{} {}", self.code, self.name)
+        }
+    }
+}
+
+/// The codes trips can be billed to, kept in the same document as the trips.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CostCentres {
+    centres: Vec<CostCentre>,
+}
+
+impl CostCentres {
+    pub fn all(&self) -> &[CostCentre] {
+        &self.centres
+    }
+    pub fn get(&self, code: &str) -> Option<&CostCentre> {
+        self.centres.iter().find(|c| c.code == code)
+    }
+    /// Adding a code that is already known renames it.
+    pub fn add(&mut self, mut centre: CostCentre) {
+        centre.code = centre.code.trim().to_owned();
+        centre.name = centre.name.trim().to_owned();
+        if centre.code.is_empty() {
+            return;
+        }
+        match self.centres.iter_mut().find(|c| c.code == centre.code) {
+            Some(known) => known.name = centre.name,
+            None => self.centres.push(centre),
+        }
+    }
+    /// Adds the codes from `other` that are not already known, keeping our names.
+    pub fn merge(&mut self, other: CostCentres) {
+        for centre in other.centres {
+            if self.get(&centre.code).is_none() {
+                self.add(centre);
+            }
+        }
+    }
+    /// Swaps in the codes from a sync, keeping the ones already known in their order.
+    pub fn replace(&mut self, mut centres: Vec<CostCentre>) {
+        centres.sort_by_cached_key(|c| {
+            let known = self.centres.iter().position(|k| k.code == c.code);
+            (known.unwrap_or(usize::MAX), c.code.clone())
+        });
+        self.centres = centres;
+    }
+    pub fn remove(&mut self, code: &str) {
+        self.centres.retain(|c| c.code != code);
+    }
+    /// A code that has since been removed is still shown as the bare code.
+    pub fn name_of(&self, code: &str) -> String {
+        self.get(code)
+            .map(CostCentre::for_humans)
+            .unwrap_or_else(|| code.to_owned())
+    }
+    /// The code last used for a trip to `destination`, if it is still in the registry.
+    pub fn suggest<'a>(
+        &self,
+        trips: impl DoubleEndedIterator<Item = &'a Trip>,
+        destination: &str,
+    ) -> Option<String> {
+        trips
+            .rev()
+            .filter(|t| t.to == destination)
+            .find_map(|t| t.cost_centre.clone())
+            .filter(|code| self.get(code).is_some())
+    }
+    /// The trips for each code, in the order the codes were added and with trips without one last.
+    pub fn split<'a>(
+        &self,
+        trips: impl IntoIterator<Item = &'a Trip>,
+    ) -> Vec<(Option<String>, Vec<&'a Trip>)> {
+        let mut groups: Vec<(Option<String>, Vec<&Trip>)> = Vec::new();
+        for trip in trips {
+            match groups
+                .iter_mut()
+                .find(|(code, _)| *code == trip.cost_centre)
+            {
+                Some((_, group)) => group.push(trip),
+                None => groups.push((trip.cost_centre.clone(), vec![trip])),
+            }
+        }
+        groups.sort_by_key(|(code, _)| {
+            code.as_ref()
+                .and_then(|code| self.centres.iter().position(|c| c.code == *code))
+                .unwrap_or(usize::MAX)
+        });
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, NaiveDate};
+
+    use super::*;
+    use crate::test_trip;
+
+    fn centres(codes: &[&str]) -> CostCentres {
+        let mut centres = CostCentres::default();
+        for code in codes {
+            centres.add(CostCentre {
+                code: code.to_string(),
+                name: String::new(),
+            });
+        }
+        centres
+    }
+
+    fn trip(day: u32, to: &str, cost_centre: Option<&str>) -> Trip {
+        Trip {
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            to: to.into(),
+            cost_centre: cost_centre.map(str::to_owned),
+            ..test_trip()
+        }
+    }
+
+    #[test]
+    fn adding_a_known_code_renames_it_and_blank_codes_are_ignored() {
+        let mut centres = centres(&["4100"]);
+        centres.add(CostCentre {
+            code: " 4100 ".into(),
+            name: " Grundskola ".into(),
+        });
+        centres.add(CostCentre {
+            code: "  ".into(),
+            name: "Ingenting".into(),
+        });
+        assert_eq!(centres.all().len(), 1);
+        assert_eq!(centres.name_of("4100"), "4100 Grundskola");
+        assert_eq!(centres.name_of("9999"), "9999");
+    }
+
+    #[test]
+    fn splits_in_the_order_codes_were_added_with_uncoded_trips_last() {
+        let centres = centres(&["4100", "4200"]);
+        let trips = [
+            trip(1, "Skolan", None),
+            trip(2, "Skolan", Some("4200")),
+            trip(3, "Skolan", Some("9999")),
+            trip(4, "Skolan", Some("4100")),
+            trip(5, "Skolan", Some("4200")),
+        ];
+        let split = centres.split(&trips);
+        let groups = split
+            .iter()
+            .map(|(code, trips)| {
+                (
+                    code.as_deref(),
+                    trips.iter().map(|t| t.date.day()).collect(),
+                )
+            })
+            .collect::<Vec<(Option<&str>, Vec<u32>)>>();
+        assert_eq!(
+            groups,
+            [
+                (Some("4100"), vec![4]),
+                (Some("4200"), vec![2, 5]),
+                // A removed code and no code at all both sort last, in the order first seen.
+                (None, vec![1]),
+                (Some("9999"), vec![3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn suggests_the_code_last_used_for_the_destination() {
+        let centres = centres(&["4100", "4200"]);
+        let trips = [
+            trip(1, "Skolan", Some("4100")),
+            trip(2, "Skolan", Some("4200")),
+            trip(3, "Skolan", None),
+            trip(4, "Biblioteket", Some("4100")),
+        ];
+        assert_eq!(
+            centres.suggest(trips.iter(), "Skolan").as_deref(),
+            Some("4200")
+        );
+        assert_eq!(centres.suggest(trips.iter(), "Simhallen"), None);
+    }
+
+    #[test]
+    fn never_suggests_a_removed_code() {
+        let mut centres = centres(&["4100", "4200"]);
+        centres.remove("4200");
+        let trips = [
+            trip(1, "Skolan", Some("4100")),
+            trip(2, "Skolan", Some("4200")),
+        ];
+        assert_eq!(centres.suggest(trips.iter(), "Skolan"), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Semicolon separated values with decimal commas, the way a Swedish spreadsheet expects them.
+
+use std::{collections::HashMap, fmt::Display};
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+use crate::{category::Category, odometer::Odometer, vehicles::Vehicles, Trip};
+
+const DELIMITER: char = ';';
+/// Stops on a rundresa share one column, a line each since no place name spans lines.
+const STOP_SEPARATOR: char = '\n';
+const HEADER: [&str; 15] = [
+    "uuid",
+    "datum",
+    "från",
+    "till",
+    "avstånd",
+    "tur och retur",
+    "totalt",
+    "restid",
+    "anledning",
+    "mätare start",
+    "mätare slut",
+    "fordon",
+    "via",
+    "kategori",
+    "kostnadsställe",
+];
+
+/// A row that could not be imported, numbered like the lines of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for RowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rad {}: {}", self.line, self.message)
+    }
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([DELIMITER, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn decimal(value: f32) -> String {
+    value.to_string().replace('.', ",")
+}
+
+/// Starts with a byte order mark, otherwise Excel mangles å, ä and ö.
+pub fn export<'a>(trips: impl IntoIterator<Item = &'a Trip>, vehicles: &Vehicles) -> String {
+    let mut out = String::from('\u{feff}');
+    out.push_str(&HEADER.join(&DELIMITER.to_string()));
+    out.push_str("\r\n");
+    for trip in trips {
+        let row = [
+            trip.uuid.to_string(),
+            trip.date.format("%Y-%m-%d").to_string(),
+            quote(&trip.from),
+            quote(&trip.to),
+            decimal(trip.distance),
+            if trip.returning { "ja" } else { "nej" }.to_owned(),
+            decimal(trip.calculate_distance()),
+            trip.time.to_string(),
+            quote(&trip.reason),
+            trip.odometer
+                .map(|o| o.start.to_string())
+                .unwrap_or_default(),
+            trip.odometer.map(|o| o.end.to_string()).unwrap_or_default(),
+            trip.vehicle
+                .and_then(|id| vehicles.get(&id))
+                .map(|v| quote(&v.registration))
+                .unwrap_or_default(),
+            quote(&trip.stops.join(&STOP_SEPARATOR.to_string())),
+            trip.category.for_humans().to_owned(),
+            quote(trip.cost_centre.as_deref().unwrap_or_default()),
+        ];
+        out.push_str(&row.join(&DELIMITER.to_string()));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Splits the text in records, keeping track of the line each record starts on.
+fn records(text: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            ('\n', true) => {
+                line += 1;
+                field.push(c);
+            }
+            (c, false) if c == delimiter => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            }
+            (c, _) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+    records.retain(|(_, r)| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+fn parse_row(
+    columns: &HashMap<String, usize>,
+    vehicles: &Vehicles,
+    row: &[String],
+) -> Result<Trip, String> {
+    let get = |name: &str| {
+        columns
+            .get(name)
+            .and_then(|i| row.get(*i))
+            .map(|f| f.trim())
+            .filter(|f| !f.is_empty())
+    };
+    let require = |name: &str| get(name).ok_or_else(|| format!("{name} saknas"));
+
+    let uuid = match get("uuid") {
+        Some(u) => Uuid::parse_str(u).map_err(|_| format!("ogiltigt uuid \"{u}\""))?,
+        None => Uuid::new_v4(),
+    };
+    let date = require("datum")?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("ogiltigt datum \"{date}\", använd ÅÅÅÅ-MM-DD"))?;
+    let distance = require("avstånd")?;
+    let distance = distance
+        .replace(',', ".")
+        .parse::<f32>()
+        .ok()
+        .filter(|d| *d > 0.)
+        .ok_or_else(|| format!("ogiltigt avstånd \"{distance}\""))?;
+    let time = require("restid")?;
+    let time = time
+        .parse::<u32>()
+        .map_err(|_| format!("ogiltig restid \"{time}\", ange hela minuter"))?;
+    let returning = match get("tur och retur").map(str::to_lowercase).as_deref() {
+        None | Some("nej") | Some("false") | Some("0") => false,
+        Some("ja") | Some("true") | Some("1") => true,
+        Some(other) => return Err(format!("ogiltigt värde för tur och retur \"{other}\"")),
+    };
+    let reading = |name: &str| {
+        get(name)
+            .map(|r| {
+                r.parse::<u32>()
+                    .map_err(|_| format!("ogiltig {name} \"{r}\""))
+            })
+            .transpose()
+    };
+    let odometer = match (reading("mätare start")?, reading("mätare slut")?) {
+        (None, None) => None,
+        (Some(start), Some(end)) => Some(
+            Odometer::new(start, end)
+                .ok_or_else(|| format!("mätare slut {end} är inte större än start {start}"))?,
+        ),
+        _ => return Err("både mätare start och mätare slut behövs".into()),
+    };
+    let vehicle = match get("fordon") {
+        Some(reg) => Some(
+            vehicles
+                .by_registration(reg)
+                .ok_or_else(|| format!("okänt fordon \"{reg}\", lägg till det först"))?
+                .id,
+        ),
+        None => None,
+    };
+    let stops = get("via")
+        .map(|v| {
+            v.split(STOP_SEPARATOR)
+                .map(|s| s.trim().to_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Files from before categories only held tjänsteresor.
+    let category = match get("kategori") {
+        Some(name) => {
+            Category::from_human(name).ok_or_else(|| format!("okänd kategori \"{name}\""))?
+        }
+        None => Category::default(),
+    };
+    Ok(Trip {
+        uuid,
+        date,
+        from: require("från")?.to_owned(),
+        stops,
+        to: require("till")?.to_owned(),
+        distance,
+        time,
+        reason: require("anledning")?.to_owned(),
+        category,
+        returning,
+        odometer,
+        vehicle,
+        cost_centre: get("kostnadsställe").map(str::to_owned),
+        edited: None,
+    })
+}
+
+/// Parses every row it can, reporting the rest by line number.
+pub fn import(text: &str, vehicles: &Vehicles) -> (Vec<Trip>, Vec<RowError>) {
+    let text = text.trim_start_matches('\u{feff}');
+    let first_line = text.lines().next().unwrap_or_default();
+    let delimiter = if first_line.contains(DELIMITER) {
+        DELIMITER
+    } else {
+        ','
+    };
+    let mut records = records(text, delimiter).into_iter();
+    let Some((_, header)) = records.next() else {
+        return (Vec::new(), Vec::new());
+    };
+    let columns: HashMap<String, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.trim().to_lowercase(), i))
+        .collect();
+    let missing: Vec<_> = ["datum", "från", "till", "avstånd", "restid", "anledning"]
+        .into_iter()
+        .filter(|c| !columns.contains_key(*c))
+        .collect();
+    if !missing.is_empty() {
+        let error = RowError {
+            line: 1,
+            message: format!("kolumner saknas: {}", missing.join(", ")),
+        };
+        return (Vec::new(), vec![error]);
+    }
+
+    let mut trips = Vec::new();
+    let mut errors = Vec::new();
+    for (line, row) in records {
+        match parse_row(&columns, vehicles, &row) {
+            Ok(trip) => trips.push(trip),
+            Err(message) => errors.push(RowError { line, message }),
+        }
+    }
+    (trips, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_trip, vehicles::Vehicle, Trips};
+
+    fn registry() -> Vehicles {
+        let mut vehicles = Vehicles::default();
+        vehicles.add(Vehicle {
+            id: Uuid::from_u128(3),
+            registration: "ABC123".into(),
+            ..Default::default()
+        });
+        vehicles
+    }
+
+    #[test]
+    fn exported_trips_import_as_the_same_trips() {
+        let vehicles = registry();
+        let full = Trip {
+            uuid: Uuid::from_u128(8),
+            stops: vec!["Biblioteket".into(), "Simhallen".into()],
+            returning: true,
+            odometer: Odometer::new(1200, 1215),
+            vehicle: Some(Uuid::from_u128(3)),
+            category: Category::Commute,
+            cost_centre: Some("4100".into()),
+            ..test_trip()
+        };
+        let trips = [test_trip(), full];
+        let (imported, errors) = import(&export(&trips, &vehicles), &vehicles);
+        assert_eq!(errors, []);
+        assert_eq!(imported, trips);
+    }
+
+    #[test]
+    fn stops_keep_slashes_in_their_names() {
+        let vehicles = Vehicles::default();
+        let trips = [Trip {
+            stops: vec!["Skola A / B".into(), "Simhallen".into()],
+            ..test_trip()
+        }];
+        let text = export(&trips, &vehicles);
+        assert!(text.contains("\"Skola A / B\nSimhallen\""));
+        let (imported, errors) = import(&text, &vehicles);
+        assert_eq!(errors, []);
+        assert_eq!(imported, trips);
+    }
+
+    #[test]
+    fn quoted_fields_keep_delimiters_quotes_and_newlines() {
+        let vehicles = Vehicles::default();
+        let trips = [Trip {
+            reason: "Möte; \"viktigt\"\noch lunch".into(),
+            ..test_trip()
+        }];
+        let text = export(&trips, &vehicles);
+        assert!(text.contains("\"Möte; \"\"viktigt\"\"\noch lunch\""));
+        let (imported, errors) = import(&text, &vehicles);
+        assert_eq!(errors, []);
+        assert_eq!(imported[0].reason, "Möte; \"viktigt\"\noch lunch");
+    }
+
+    #[test]
+    fn reads_commas_when_the_header_has_no_semicolon() {
+        let text = "datum,från,till,avstånd,restid,anledning\n\
+                    2024-03-01,Kommunhuset,Ekenässkolan,3.2,7,Möte\n";
+        let (imported, errors) = import(text, &Vehicles::default());
+        assert_eq!(errors, []);
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].distance, 3.2);
+        assert_eq!(imported[0].to, "Ekenässkolan");
+    }
+
+    #[test]
+    fn reports_bad_rows_by_the_line_they_start_on() {
+        let text = "datum;från;till;avstånd;restid;anledning\n\
+                    2024-03-01;Kommunhuset;Ekenässkolan;3,2;7;\"Möte\nmed rektor\"\n\
+                    2024-13-01;Kommunhuset;Ekenässkolan;3,2;7;Möte\n\
+                    \n\
+                    2024-03-02;Kommunhuset;Ekenässkolan;;7;Möte\n\
+                    2024-03-03;Kommunhuset;Ekenässkolan;3,2;7;Möte\n";
+        let (imported, errors) = import(text, &Vehicles::default());
+        assert_eq!(imported.len(), 2);
+        let lines = errors.iter().map(|e| e.line).collect::<Vec<_>>();
+        assert_eq!(lines, [4, 6]);
+        assert!(errors[0].message.contains("datum"));
+        assert_eq!(errors[1].to_string(), "Rad 6: avstånd saknas");
+    }
+
+    #[test]
+    fn refuses_files_without_the_needed_columns() {
+        let (imported, errors) = import("datum;från;till\n2024-03-01;A;B\n", &Vehicles::default());
+        assert!(imported.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+        assert!(errors[0].message.contains("avstånd, restid, anledning"));
+    }
+
+    #[test]
+    fn refuses_vehicles_that_are_not_in_the_registry() {
+        let text = export(
+            [&Trip {
+                vehicle: Some(Uuid::from_u128(3)),
+                ..test_trip()
+            }],
+            &registry(),
+        );
+        let (imported, errors) = import(&text, &Vehicles::default());
+        assert!(imported.is_empty());
+        assert!(errors[0].message.contains("okänt fordon \"ABC123\""));
+    }
+
+    #[test]
+    fn importing_the_same_file_twice_adds_nothing_new() {
+        let vehicles = Vehicles::default();
+        let text = export(&[test_trip()], &vehicles);
+        let mut trips = Trips::default();
+        assert_eq!(trips.merge(import(&text, &vehicles).0), 1);
+        assert_eq!(trips.merge(import(&text, &vehicles).0), 0);
+        assert_eq!(trips.trips.len(), 1);
+    }
+}
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use itertools::Itertools;
+use leptos::*;
+use leptos_use::{storage::use_local_storage, utils::JsonCodec};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
@@ -38,8 +41,16 @@ impl Travel {
         (((self.distance.value as f32 / 1000.) * 10.).round()) / 10.
     }
     pub fn minutes(&self) -> u32 {
-        self.duration.value / 60
+        minutes(self.duration.value)
     }
+    pub fn describe(&self) -> String {
+        format!("{}, {}", self.distance.text, self.duration.text)
+    }
+}
+
+/// Seconds to the nearest minute, the same for every route however it was found.
+fn minutes(seconds: u32) -> u32 {
+    (seconds + 30) / 60
 }
 
 impl From<&Trip> for Travel {
@@ -50,22 +61,704 @@ impl From<&Trip> for Travel {
     }
 }
 
-type Destinations = HashMap<String, HashMap<String, Vec<Travel>>>;
+/// Where a destination is, in degrees.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Coordinates {
+    /// Distance as the crow flies, in kilometres.
+    pub fn great_circle_km(&self, other: &Coordinates) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.;
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+        let a = (d_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.).sin().powi(2);
+        2. * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// Distances and times the user entered for pairs missing from the matrix.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CustomTrips {
+    trips: HashMap<String, HashMap<String, Travel>>,
+    /// Places the user has positioned themselves, used to estimate unknown pairs.
+    #[serde(default)]
+    coordinates: HashMap<String, Coordinates>,
+    /// The user's own names for places, pointing at the name used everywhere else.
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+    /// Street addresses, just for the user's reference.
+    #[serde(default)]
+    addresses: HashMap<String, String>,
+}
+
+impl CustomTrips {
+    pub fn get(&self, from: &str, to: &str) -> Option<Travel> {
+        self.trips.get(from)?.get(to).cloned()
+    }
+    pub fn add(&mut self, trip: &Trip) {
+        let fr = trip.from.to_owned();
+        let to = trip.to.to_owned();
+        let tra: Travel = trip.into();
+        self.trips.entry(fr).or_default().insert(to, tra);
+    }
+    /// Every place the user has driven from or to.
+    pub fn places(&self) -> impl Iterator<Item = &String> {
+        self.trips
+            .keys()
+            .chain(self.trips.values().flat_map(HashMap::keys))
+            .unique()
+    }
+    pub fn coordinates(&self) -> &HashMap<String, Coordinates> {
+        &self.coordinates
+    }
+    pub fn set_coordinates(&mut self, place: String, coordinates: Coordinates) {
+        self.coordinates.insert(place, coordinates);
+    }
+    pub fn remove_coordinates(&mut self, place: &str) {
+        self.coordinates.remove(place);
+    }
+    pub fn aliases(&self) -> &HashMap<String, String> {
+        &self.aliases
+    }
+    pub fn set_alias(&mut self, alias: String, place: String) {
+        self.aliases.insert(alias, place);
+    }
+    pub fn remove_alias(&mut self, alias: &str) {
+        self.aliases.remove(alias);
+    }
+    pub fn address(&self, place: &str) -> Option<&String> {
+        self.addresses.get(place)
+    }
+    /// Forgets the address when it is set to nothing.
+    pub fn set_address(&mut self, place: String, address: String) {
+        if address.trim().is_empty() {
+            self.addresses.remove(&place);
+        } else {
+            self.addresses.insert(place, address.trim().to_owned());
+        }
+    }
+    /// Moves everything known about `old` over to `new`, keeping what `new` already had.
+    pub fn rename_place(&mut self, old: &str, new: &str) {
+        if old == new {
+            return;
+        }
+        if let Some(tos) = self.trips.remove(old) {
+            let known = self.trips.entry(new.to_owned()).or_default();
+            for (to, travel) in tos {
+                known.entry(to).or_insert(travel);
+            }
+        }
+        for tos in self.trips.values_mut() {
+            rekey(tos, old, new);
+        }
+        // A place is no distance from itself.
+        if let Some(tos) = self.trips.get_mut(new) {
+            tos.remove(new);
+        }
+        self.trips.retain(|_, tos| !tos.is_empty());
+        rekey(&mut self.coordinates, old, new);
+        rekey(&mut self.addresses, old, new);
+        for place in self.aliases.values_mut() {
+            if place == old {
+                *place = new.to_owned();
+            }
+        }
+    }
+    /// Forgets every distance, position, address and alias the user entered for `place`.
+    pub fn remove_place(&mut self, place: &str) {
+        self.trips.remove(place);
+        for tos in self.trips.values_mut() {
+            tos.remove(place);
+        }
+        self.trips.retain(|_, tos| !tos.is_empty());
+        self.coordinates.remove(place);
+        self.addresses.remove(place);
+        self.aliases.retain(|_, p| p != place);
+    }
+    /// Whether the user has entered anything at all about `place`.
+    pub fn knows(&self, place: &str) -> bool {
+        self.places().any(|p| p == place)
+            || self.coordinates.contains_key(place)
+            || self.addresses.contains_key(place)
+            || self.aliases.values().any(|p| p == place)
+    }
+    /// The user's own coordinates for `place`, or the ones shipped with the app.
+    pub fn locate(&self, place: &str) -> Option<Coordinates> {
+        self.coordinates
+            .get(place)
+            .or_else(|| COORDINATES.get(place))
+            .copied()
+    }
+    /// Adds the pairs from `other` that are missing here, keeping our own corrections.
+    pub fn merge(&mut self, other: CustomTrips) {
+        for (from, tos) in other.trips {
+            let known = self.trips.entry(from).or_default();
+            for (to, travel) in tos {
+                known.entry(to).or_insert(travel);
+            }
+        }
+        for (place, coordinates) in other.coordinates {
+            self.coordinates.entry(place).or_insert(coordinates);
+        }
+        for (alias, place) in other.aliases {
+            self.aliases.entry(alias).or_insert(place);
+        }
+        for (place, address) in other.addresses {
+            self.addresses.entry(place).or_insert(address);
+        }
+    }
+}
+
+/// Moves the value under `old` to `new`, unless `new` already has one.
+fn rekey<V>(map: &mut HashMap<String, V>, old: &str, new: &str) {
+    if let Some(value) = map.remove(old) {
+        map.entry(new.to_owned()).or_insert(value);
+    }
+}
+
+pub fn use_custom_trips() -> (Signal<CustomTrips>, WriteSignal<CustomTrips>) {
+    let (r_custom, w_custom, _) = use_local_storage::<CustomTrips, JsonCodec>("my-custom-trips");
+    (r_custom, w_custom)
+}
+
+// Generated by build.rs from data/matrix.json: the sorted place names, and metres and seconds
+// for every ordered pair in row-major order.
+include!(concat!(env!("OUT_DIR"), "/matrix.rs"));
+
+/// Position of each place in the tables.
+static INDEX: Lazy<HashMap<&'static str, usize>> =
+    Lazy::new(|| NAMES.iter().enumerate().map(|(i, n)| (*n, i)).collect());
+
+/// Where the matrix places are, from `data/coordinates.json` as filled in by `prepare`.
+static COORDINATES: Lazy<HashMap<String, Coordinates>> = Lazy::new(|| {
+    let file = include_bytes!("../data/coordinates.json");
+    serde_json::from_slice(file).unwrap_or_default()
+});
 
-static DESTINATIONS: Lazy<Option<Destinations>> = Lazy::new(|| {
-    let file = include_bytes!("../data/matrix.json");
-    let json: Destinations = serde_json::from_slice(file).ok()?;
-    Some(json)
+/// Nicknames shipped with the app, from `data/aliases.json`.
+pub static ALIASES: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    let file = include_bytes!("../data/aliases.json");
+    serde_json::from_slice(file).unwrap_or_default()
 });
 
+/// Whether `place` is one of the places shipped in the matrix, which cannot be renamed.
+pub fn in_matrix(place: &str) -> bool {
+    INDEX.contains_key(place)
+}
+
 pub fn destinations() -> Vec<String> {
-    DESTINATIONS
-        .as_ref()
-        .map_or_else(Vec::new, |x| x.keys().map(Clone::clone).collect())
+    NAMES.iter().map(|n| n.to_string()).collect()
 }
 
 pub fn travel(from: &str, to: &str) -> Option<Travel> {
-    let travel = DESTINATIONS.as_ref()?.get(from)?.get(to)?.iter().next()?;
+    let cell = INDEX.get(from)? * NAMES.len() + INDEX.get(to)?;
+    let (metres, seconds) = (DISTANCES[cell], DURATIONS[cell]);
+    if metres == MISSING || seconds == MISSING {
+        return None;
+    }
+    let distance = Distance::new(
+        format!("{:.1} km", metres as f32 / 1000.).replace('.', ","),
+        metres,
+    );
+    let duration = Duration::new(format!("{} min", minutes(seconds)), seconds);
+    Some(Travel::new(distance, duration))
+}
+
+/// Which kind of knowledge a resolved travel came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Entered by the user, which beats the matrix since they have driven it.
+    Custom,
+    Matrix,
+    /// Two known legs through this place.
+    Via(String),
+}
+
+impl Source {
+    pub fn for_humans(&self) -> String {
+        match self {
+            Self::Custom => "egen uppgift".to_owned(),
+            Self::Matrix => "avståndstabellen".to_owned(),
+            Self::Via(place) => format!("via {place}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub travel: Travel,
+    /// One per leg, without repeats.
+    pub sources: Vec<Source>,
+}
+
+impl Resolved {
+    pub fn describe_sources(&self) -> String {
+        self.sources.iter().map(Source::for_humans).join(", ")
+    }
+}
+
+/// Answers how far it is between two places from everything the app knows.
+///
+/// A road is as long in both directions, so every source is asked both ways round. When
+/// `compose` is set, a missing pair may be bridged through a single known intermediate place.
+pub struct TravelResolver<'a> {
+    custom: &'a CustomTrips,
+    compose: bool,
+}
+
+impl<'a> TravelResolver<'a> {
+    pub fn new(custom: &'a CustomTrips, compose: bool) -> Self {
+        Self { custom, compose }
+    }
+
+    /// The matrix in either direction.
+    pub fn matrix(&self, from: &str, to: &str) -> Option<Travel> {
+        travel(from, to).or_else(|| travel(to, from))
+    }
+
+    /// A pair someone actually knows, without composing.
+    pub fn direct(&self, from: &str, to: &str) -> Option<(Travel, Source)> {
+        let custom = self
+            .custom
+            .get(from, to)
+            .or_else(|| self.custom.get(to, from));
+        custom
+            .map(|t| (t, Source::Custom))
+            .or_else(|| Some((self.matrix(from, to)?, Source::Matrix)))
+    }
+
+    pub fn resolve(&self, from: &str, to: &str) -> Option<(Travel, Source)> {
+        self.direct(from, to)
+            .or_else(|| self.compose.then(|| self.shortest_via(from, to)).flatten())
+    }
+
+    /// Every leg of `route` resolved and added up, or `None` if any leg is unknown.
+    pub fn route(&self, route: &[String]) -> Option<Resolved> {
+        let mut sources = Vec::new();
+        let legs = route.windows(2).map(|w| {
+            let (travel, source) = self.resolve(&w[0], &w[1])?;
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+            Some(travel)
+        });
+        let travel = total(legs.collect::<Vec<_>>())?;
+        Some(Resolved { travel, sources })
+    }
 
-    Some(travel.clone())
+    fn shortest_via(&self, from: &str, to: &str) -> Option<(Travel, Source)> {
+        let places = NAMES
+            .iter()
+            .copied()
+            .chain(self.custom.places().map(String::as_str))
+            .filter(|p| *p != from && *p != to)
+            .unique();
+        places
+            .filter_map(|via| {
+                let (first, _) = self.direct(from, via)?;
+                let (second, _) = self.direct(via, to)?;
+                Some((total([Some(first), Some(second)])?, via))
+            })
+            .min_by_key(|(t, _)| t.distance.value)
+            .map(|(t, via)| (t, Source::Via(via.to_owned())))
+    }
+}
+
+/// Adds up the legs of a route, or `None` if any leg is unknown.
+pub fn total(legs: impl IntoIterator<Item = Option<Travel>>) -> Option<Travel> {
+    let (meters, seconds) = legs.into_iter().try_fold((0, 0), |(m, s), leg| {
+        let leg = leg?;
+        Some((m + leg.distance.value, s + leg.duration.value))
+    })?;
+    let distance = Distance::new(
+        format!("{:.1} km", meters as f32 / 1000.).replace('.', ","),
+        meters,
+    );
+    let duration = Duration::new(format!("{} min", minutes(seconds)), seconds);
+    Some(Travel::new(distance, duration))
+}
+
+/// A rough guess from the great-circle distance, for pairs nobody has driven yet.
+pub fn estimate(
+    from: Coordinates,
+    to: Coordinates,
+    winding_factor: f32,
+    average_speed: f32,
+) -> Travel {
+    let km = from.great_circle_km(&to) * f64::from(winding_factor);
+    let seconds = km / f64::from(average_speed.max(1.)) * 3600.;
+    let distance = Distance::new(
+        format!("ca {km:.1} km").replace('.', ","),
+        (km * 1000.) as u32,
+    );
+    let seconds = seconds.round() as u32;
+    let duration = Duration::new(format!("ca {} min", minutes(seconds)), seconds);
+    Travel::new(distance, duration)
+}
+
+/// Lowercase with diacritics, spaces and punctuation dropped, so that "Plåten-gymnasiet" and
+/// "platengymnasiet" compare equal.
+pub fn fold(name: &str) -> String {
+    name.chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| match c {
+            'å' | 'ä' | 'à' | 'á' | 'â' | 'ã' => 'a',
+            'ö' | 'ø' | 'ò' | 'ó' | 'ô' | 'õ' => 'o',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'ü' | 'ú' | 'ù' | 'û' => 'u',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ç' => 'c',
+            'ñ' => 'n',
+            c => c,
+        })
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != *cb))
+                .min(row[j] + 1)
+                .min(above + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Finds the name a place is known by from whatever the user typed.
+pub struct PlaceMatcher {
+    /// Folded name and the name itself.
+    names: Vec<(String, String)>,
+    /// Folded alias and the name it stands for.
+    aliases: Vec<(String, String)>,
+}
+
+impl PlaceMatcher {
+    pub fn new(custom: &CustomTrips) -> Self {
+        let names = NAMES
+            .iter()
+            .map(|n| n.to_string())
+            .chain(custom.places().cloned())
+            .unique()
+            .map(|n| (fold(&n), n))
+            .collect();
+        // The user's own aliases win over the shipped ones.
+        let aliases = custom
+            .aliases
+            .iter()
+            .chain(ALIASES.iter())
+            .map(|(alias, place)| (fold(alias), place.clone()))
+            .collect();
+        Self { names, aliases }
+    }
+
+    /// The known name `typed` certainly means: the name itself in any case or spelling of å, ä
+    /// and ö, or an alias of it.
+    pub fn canonical(&self, typed: &str) -> Option<String> {
+        let typed = typed.trim();
+        if typed.is_empty() {
+            return None;
+        }
+        if let Some((_, name)) = self.names.iter().find(|(_, n)| n == typed) {
+            return Some(name.clone());
+        }
+        let folded = fold(typed);
+        let exact = |list: &[(String, String)]| {
+            list.iter()
+                .find(|(f, _)| *f == folded)
+                .map(|(_, n)| n.clone())
+        };
+        exact(&self.names).or_else(|| exact(&self.aliases))
+    }
+
+    /// A known name `typed` might be short for or a misspelling of. Only offered, never applied,
+    /// since it may just as well be a new place.
+    pub fn suggest(&self, typed: &str) -> Option<String> {
+        let typed = typed.trim();
+        if typed.is_empty() || self.canonical(typed).is_some() {
+            return None;
+        }
+        let folded = fold(typed);
+        // The beginning of a single name, such as "Platen" for "Platengymnasiet".
+        if folded.chars().count() >= 4 {
+            let mut starting = self.names.iter().filter(|(f, _)| f.starts_with(&folded));
+            if let (Some((_, name)), None) = (starting.next(), starting.next()) {
+                return Some(name.clone());
+            }
+        }
+        // A typo or two, as long as no other name is as close.
+        let allowed = (folded.chars().count() / 6).clamp(1, 2);
+        let mut close = self
+            .names
+            .iter()
+            .map(|(f, n)| (edit_distance(&folded, f), n))
+            .filter(|(d, _)| *d <= allowed)
+            .sorted_by_key(|(d, _)| *d);
+        match (close.next(), close.next()) {
+            (Some((best, name)), next) if next.is_none_or(|(d, _)| d > best) => {
+                Some(name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// `typed` as its known name, or unchanged if there is none.
+    pub fn resolve(&self, typed: &str) -> String {
+        self.canonical(typed)
+            .unwrap_or_else(|| typed.trim().to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leg(metres: u32, seconds: u32) -> Travel {
+        Travel::new(
+            Distance::new(String::new(), metres),
+            Duration::new(String::new(), seconds),
+        )
+    }
+
+    #[test]
+    fn legs_and_totals_round_minutes_alike() {
+        assert_eq!(leg(1000, 89).minutes(), 1);
+        assert_eq!(leg(1000, 90).minutes(), 2);
+        let route = total([Some(leg(1000, 90)), Some(leg(1000, 90))]).unwrap();
+        assert_eq!(route.minutes(), 3);
+        assert_eq!(route.duration.text, "3 min");
+    }
+
+    /// Custom trips between made-up places, so the matrix never gets a say.
+    fn driven(legs: &[(&str, &str, f32, u32)]) -> CustomTrips {
+        let mut custom = CustomTrips::default();
+        for &(from, to, distance, time) in legs {
+            custom.add(&Trip {
+                from: from.into(),
+                to: to.into(),
+                distance,
+                time,
+                ..crate::test_trip()
+            });
+        }
+        custom
+    }
+
+    #[test]
+    fn a_lookup_is_the_same_both_ways_round() {
+        let custom = driven(&[("Gård A", "Gård B", 4.5, 6)]);
+        let resolver = TravelResolver::new(&custom, false);
+        let there = resolver.resolve("Gård A", "Gård B").unwrap();
+        assert_eq!(resolver.resolve("Gård B", "Gård A"), Some(there.clone()));
+        assert_eq!((there.0.km(), there.0.minutes()), (4.5, 6));
+    }
+
+    #[test]
+    fn a_custom_trip_beats_the_matrix() {
+        let (from, to) = ("Kommunhuset", "Ekenässkolan");
+        let custom = driven(&[(to, from, 9.9, 20)]);
+        let resolver = TravelResolver::new(&custom, false);
+        assert!(resolver.matrix(from, to).is_some());
+        let (travel, source) = resolver.resolve(from, to).unwrap();
+        assert_eq!(
+            (travel.km(), travel.minutes(), source),
+            (9.9, 20, Source::Custom)
+        );
+    }
+
+    #[test]
+    fn a_route_adds_up_its_legs() {
+        let custom = driven(&[("Gård A", "Gård B", 2., 3), ("Gård C", "Gård B", 1.5, 2)]);
+        let resolver = TravelResolver::new(&custom, false);
+        let stops = ["Gård A", "Gård B", "Gård C", "Gård B"].map(String::from);
+        let resolved = resolver.route(&stops).unwrap();
+        assert_eq!((resolved.travel.km(), resolved.travel.minutes()), (5., 7));
+        assert_eq!(resolved.sources, [Source::Custom]);
+        let lost = ["Gård A", "Gård B", "Gård D"].map(String::from);
+        assert_eq!(resolver.route(&lost), None);
+    }
+
+    #[test]
+    fn composing_goes_through_the_nearest_place() {
+        let custom = driven(&[
+            ("Gård A", "Gård B", 2., 3),
+            ("Gård B", "Gård D", 2., 3),
+            ("Gård A", "Gård C", 1., 2),
+            ("Gård C", "Gård D", 1.5, 2),
+        ]);
+        assert_eq!(
+            TravelResolver::new(&custom, false).resolve("Gård A", "Gård D"),
+            None
+        );
+        let (travel, source) = TravelResolver::new(&custom, true)
+            .resolve("Gård A", "Gård D")
+            .unwrap();
+        assert_eq!((travel.km(), source), (2.5, Source::Via("Gård C".into())));
+    }
+
+    #[test]
+    fn renaming_a_place_moves_everything_known_about_it() {
+        let mut custom = driven(&[("Skolan", "Gård B", 2., 3), ("Gård C", "Skolan", 1., 2)]);
+        let spot = Coordinates {
+            lat: 58.5,
+            lon: 15.,
+        };
+        custom.set_coordinates("Skolan".into(), spot);
+        custom.set_address("Skolan".into(), "Skolvägen 1".into());
+        custom.set_alias("skolan vid sjön".into(), "Skolan".into());
+        custom.rename_place("Skolan", "Ekenässkolan");
+        assert!(!custom.knows("Skolan"));
+        assert!(custom.get("Ekenässkolan", "Gård B").is_some());
+        assert!(custom.get("Gård C", "Ekenässkolan").is_some());
+        assert_eq!(custom.coordinates().get("Ekenässkolan"), Some(&spot));
+        assert_eq!(custom.address("Ekenässkolan").unwrap(), "Skolvägen 1");
+        assert_eq!(custom.aliases()["skolan vid sjön"], "Ekenässkolan");
+    }
+
+    #[test]
+    fn renaming_onto_a_known_place_merges_without_overwriting() {
+        let mut custom = driven(&[
+            ("Skolan", "Gård B", 9., 9),
+            ("Skolan", "Gård C", 1., 2),
+            ("Ekenässkolan", "Gård B", 2., 3),
+            ("Skolan", "Ekenässkolan", 0.5, 1),
+        ]);
+        custom.set_address("Skolan".into(), "Fel väg 1".into());
+        custom.set_address("Ekenässkolan".into(), "Skolvägen 1".into());
+        custom.rename_place("Skolan", "Ekenässkolan");
+        assert_eq!(custom.get("Ekenässkolan", "Gård B").unwrap().km(), 2.);
+        assert_eq!(custom.get("Ekenässkolan", "Gård C").unwrap().km(), 1.);
+        assert_eq!(custom.get("Ekenässkolan", "Ekenässkolan"), None);
+        assert_eq!(custom.address("Ekenässkolan").unwrap(), "Skolvägen 1");
+    }
+
+    #[test]
+    fn removing_a_place_forgets_it_everywhere() {
+        let mut custom = driven(&[("Skolan", "Gård B", 2., 3), ("Gård C", "Skolan", 1., 2)]);
+        custom.set_coordinates("Skolan".into(), Coordinates::default());
+        custom.set_address("Skolan".into(), "Skolvägen 1".into());
+        custom.set_alias("skolan vid sjön".into(), "Skolan".into());
+        custom.set_alias("gården".into(), "Gård B".into());
+        custom.remove_place("Skolan");
+        assert!(!custom.knows("Skolan"));
+        assert_eq!(custom.places().count(), 0);
+        assert!(custom.coordinates().is_empty());
+        assert_eq!(custom.aliases().len(), 1);
+    }
+
+    #[test]
+    fn known_places_are_estimated_once_located() {
+        let (from, to) = ("Kommunhuset", "Ekenässkolan");
+        assert!(in_matrix(from) && in_matrix(to));
+        let mut custom = CustomTrips::default();
+        for (place, lat) in [(from, 58.53), (to, 58.54)] {
+            custom.set_coordinates(place.into(), Coordinates { lat, lon: 15.04 });
+        }
+        let guess = |custom: &CustomTrips| {
+            Some(estimate(custom.locate(from)?, custom.locate(to)?, 1.3, 50.))
+        };
+        // A hundredth of a degree north is 1,1 km, and the road a third longer.
+        let travel = guess(&custom).unwrap();
+        assert_eq!(travel.distance.text, "ca 1,4 km");
+        assert_eq!(travel.minutes(), 2);
+        // Without the user's own, the shipped coordinates are all there is.
+        custom.remove_coordinates(to);
+        assert_eq!(custom.locate(to), COORDINATES.get(to).copied());
+    }
+
+    fn matcher(names: &[&str], aliases: &[(&str, &str)]) -> PlaceMatcher {
+        PlaceMatcher {
+            names: names.iter().map(|n| (fold(n), n.to_string())).collect(),
+            aliases: aliases
+                .iter()
+                .map(|(alias, place)| (fold(alias), place.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn folding_drops_case_diacritics_and_punctuation() {
+        assert_eq!(fold("Plåten-gymnasiet"), "platengymnasiet");
+        assert_eq!(fold("  ÖREBRO Län "), "orebrolan");
+        assert_eq!(fold("Café Noël"), "cafenoel");
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("skola", "skola"), 0);
+        assert_eq!(edit_distance("skola", "skolan"), 1);
+        assert_eq!(edit_distance("skola", "skila"), 1);
+        assert_eq!(edit_distance("skola", "sklao"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn certain_matches_ignore_case_and_diacritics() {
+        let m = matcher(
+            &["Ekenässkolan", "Kommunhuset"],
+            &[("Kommun", "Kommunhuset")],
+        );
+        assert_eq!(m.canonical("Ekenässkolan").as_deref(), Some("Ekenässkolan"));
+        assert_eq!(
+            m.canonical(" ekenasskolan ").as_deref(),
+            Some("Ekenässkolan")
+        );
+        assert_eq!(
+            m.canonical("EKENÄS-SKOLAN").as_deref(),
+            Some("Ekenässkolan")
+        );
+        assert_eq!(m.canonical("kommun").as_deref(), Some("Kommunhuset"));
+        assert_eq!(m.canonical("Ekenässkola"), None);
+        assert_eq!(m.canonical("  "), None);
+    }
+
+    #[test]
+    fn names_win_over_aliases() {
+        let m = matcher(
+            &["Biblioteket", "Stadsbiblioteket"],
+            &[("Biblioteket", "Stadsbiblioteket")],
+        );
+        assert_eq!(m.canonical("biblioteket").as_deref(), Some("Biblioteket"));
+    }
+
+    #[test]
+    fn near_misses_are_only_suggested() {
+        let m = matcher(&["Ekenässkolan", "Platengymnasiet"], &[]);
+        assert_eq!(m.suggest("Platen").as_deref(), Some("Platengymnasiet"));
+        assert_eq!(m.suggest("Ekenäsksolan").as_deref(), Some("Ekenässkolan"));
+        assert_eq!(m.resolve("Ekenäsksolan"), "Ekenäsksolan");
+        // A certain match needs no suggestion.
+        assert_eq!(m.suggest("ekenasskolan"), None);
+        // Too short to be the start of a name, and too far off to be a typo.
+        assert_eq!(m.suggest("Pla"), None);
+        assert_eq!(m.suggest("Simhallen"), None);
+    }
+
+    #[test]
+    fn ties_suggest_nothing() {
+        let m = matcher(&["Skola A", "Skola B", "Skolgården"], &[]);
+        // Starts two names, and is one edit from both.
+        assert_eq!(m.suggest("Skola"), None);
+        assert_eq!(m.suggest("Skola C"), None);
+        // Starts only one.
+        assert_eq!(m.suggest("Skolg").as_deref(), Some("Skolgården"));
+    }
+
+    #[test]
+    fn resolve_keeps_unknown_places_as_typed_but_trimmed() {
+        let m = matcher(&["Kommunhuset"], &[]);
+        assert_eq!(m.resolve(" kommunhuset"), "Kommunhuset");
+        assert_eq!(m.resolve(" Simhallen "), "Simhallen");
+    }
 }
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::Trip;
+
+/// A route as the favourites see it: the other way round, or there and back, is another route.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Route {
+    pub from: String,
+    pub to: String,
+    pub returning: bool,
+}
+
+impl Route {
+    pub fn of(trip: &Trip) -> Self {
+        Self {
+            from: trip.from.clone(),
+            to: trip.to.clone(),
+            returning: trip.returning,
+        }
+    }
+    pub fn for_humans(&self) -> String {
+        let arrow = if self.returning { "↔" } else { "→" };
+        format!("{} {arrow} {}", self.from, self.to)
+    }
+}
+
+/// How favourites are picked, and the ones the user pinned or hid.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Favorites {
+    /// Days after which a trip counts half as much towards its route.
+    #[serde(default = "default_half_life")]
+    pub half_life: u32,
+    /// How many favourites to show, unless more than that are pinned.
+    #[serde(default = "default_shown")]
+    pub shown: usize,
+    /// Always shown first, in the order they were pinned.
+    #[serde(default)]
+    pub pinned: Vec<Route>,
+    /// Never shown, however often they are driven.
+    #[serde(default)]
+    pub hidden: Vec<Route>,
+}
+
+fn default_half_life() -> u32 {
+    60
+}
+
+fn default_shown() -> usize {
+    5
+}
+
+impl Default for Favorites {
+    fn default() -> Self {
+        Self {
+            half_life: default_half_life(),
+            shown: default_shown(),
+            pinned: Vec::new(),
+            hidden: Vec::new(),
+        }
+    }
+}
+
+impl Favorites {
+    pub fn is_pinned(&self, route: &Route) -> bool {
+        self.pinned.contains(route)
+    }
+    pub fn toggle_pin(&mut self, route: Route) {
+        if self.is_pinned(&route) {
+            self.pinned.retain(|r| *r != route);
+        } else {
+            self.hidden.retain(|r| *r != route);
+            self.pinned.push(route);
+        }
+    }
+    pub fn hide(&mut self, route: Route) {
+        self.pinned.retain(|r| *r != route);
+        if !self.hidden.contains(&route) {
+            self.hidden.push(route);
+        }
+    }
+    pub fn unhide(&mut self, route: &Route) {
+        self.hidden.retain(|r| r != route);
+    }
+    /// Halves for every `half_life` days since the trip was made.
+    fn weight(&self, date: NaiveDate, today: NaiveDate) -> f32 {
+        let age = (today - date).num_days().max(0) as f32;
+        0.5_f32.powf(age / self.half_life.max(1) as f32)
+    }
+    /// The pinned routes followed by the ones driven most lately, each as the last trip made on it.
+    pub fn rank<'a>(&self, trips: &'a [Trip], today: NaiveDate) -> Vec<&'a Trip> {
+        let mut scores: HashMap<Route, (f32, &Trip)> = HashMap::new();
+        for trip in trips {
+            let weight = self.weight(trip.date, today);
+            let (score, last) = scores.entry(Route::of(trip)).or_insert((0., trip));
+            *score += weight;
+            if trip.date >= last.date {
+                *last = trip;
+            }
+        }
+        let pinned = self
+            .pinned
+            .iter()
+            .filter_map(|r| scores.get(r).map(|(_, t)| *t))
+            .collect_vec();
+        let rest = scores
+            .iter()
+            .filter(|(r, _)| !self.is_pinned(r) && !self.hidden.contains(r))
+            .sorted_by(|a, b| {
+                b.1 .0.total_cmp(&a.1 .0).then_with(|| {
+                    (&a.0.from, &a.0.to, a.0.returning).cmp(&(&b.0.from, &b.0.to, b.0.returning))
+                })
+            })
+            .map(|(_, (_, t))| *t)
+            .take(self.shown.saturating_sub(pinned.len()))
+            .collect_vec();
+        pinned.into_iter().chain(rest).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_trip;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    }
+
+    fn trip(from: &str, to: &str, days_ago: i64) -> Trip {
+        Trip {
+            date: today() - chrono::Duration::days(days_ago),
+            from: from.into(),
+            to: to.into(),
+            ..test_trip()
+        }
+    }
+
+    fn route(from: &str, to: &str) -> Route {
+        Route {
+            from: from.into(),
+            to: to.into(),
+            returning: false,
+        }
+    }
+
+    fn ranked(favorites: &Favorites, trips: &[Trip]) -> Vec<String> {
+        favorites
+            .rank(trips, today())
+            .into_iter()
+            .map(|t| Route::of(t).for_humans())
+            .collect()
+    }
+
+    #[test]
+    fn a_trip_counts_half_after_each_half_life() {
+        let favorites = Favorites::default();
+        let weight =
+            |days_ago| favorites.weight(today() - chrono::Duration::days(days_ago), today());
+        assert_eq!(weight(0), 1.);
+        assert_eq!(weight(60), 0.5);
+        assert_eq!(weight(120), 0.25);
+        // Trips dated ahead count as today.
+        assert_eq!(weight(-10), 1.);
+    }
+
+    #[test]
+    fn recent_routes_beat_ones_driven_more_long_ago() {
+        let trips = [
+            trip("A", "B", 300),
+            trip("A", "B", 310),
+            trip("A", "B", 320),
+            trip("A", "C", 1),
+        ];
+        assert_eq!(ranked(&Favorites::default(), &trips), ["A → C", "A → B"]);
+    }
+
+    #[test]
+    fn each_route_is_offered_as_its_last_trip() {
+        let trips = [trip("A", "B", 5), trip("A", "B", 1), trip("A", "B", 3)];
+        let rank = Favorites::default().rank(&trips, today());
+        assert_eq!(rank.len(), 1);
+        assert_eq!(rank[0].date, today() - chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn ties_are_broken_by_name_and_then_by_direction() {
+        let trips = [
+            Trip {
+                returning: true,
+                ..trip("A", "C", 2)
+            },
+            trip("A", "C", 2),
+            trip("A", "B", 2),
+        ];
+        for _ in 0..10 {
+            assert_eq!(
+                ranked(&Favorites::default(), &trips),
+                ["A → B", "A → C", "A ↔ C"]
+            );
+        }
+    }
+
+    #[test]
+    fn pinned_routes_come_first_in_pin_order_and_hidden_ones_never() {
+        let mut favorites = Favorites::default();
+        favorites.toggle_pin(route("C", "D"));
+        favorites.toggle_pin(route("A", "C"));
+        favorites.hide(route("A", "B"));
+        let trips = [
+            trip("A", "B", 1),
+            trip("A", "C", 200),
+            trip("B", "C", 2),
+            trip("C", "D", 300),
+        ];
+        assert_eq!(ranked(&favorites, &trips), ["C → D", "A → C", "B → C"]);
+    }
+
+    #[test]
+    fn pinned_routes_never_driven_take_no_place() {
+        let mut favorites = Favorites {
+            shown: 2,
+            ..Default::default()
+        };
+        favorites.toggle_pin(route("X", "Y"));
+        let trips = [trip("A", "B", 1), trip("A", "C", 2), trip("B", "C", 3)];
+        assert_eq!(ranked(&favorites, &trips), ["A → B", "A → C"]);
+    }
+
+    #[test]
+    fn pinning_more_than_are_shown_shows_every_pin() {
+        let mut favorites = Favorites {
+            shown: 1,
+            ..Default::default()
+        };
+        favorites.toggle_pin(route("A", "B"));
+        favorites.toggle_pin(route("A", "C"));
+        let trips = [trip("A", "B", 1), trip("A", "C", 2), trip("B", "C", 0)];
+        assert_eq!(ranked(&favorites, &trips), ["A → B", "A → C"]);
+    }
+
+    #[test]
+    fn pinning_unhides_and_hiding_unpins() {
+        let mut favorites = Favorites::default();
+        favorites.hide(route("A", "B"));
+        favorites.toggle_pin(route("A", "B"));
+        assert!(favorites.is_pinned(&route("A", "B")));
+        assert!(favorites.hidden.is_empty());
+        favorites.hide(route("A", "B"));
+        assert!(!favorites.is_pinned(&route("A", "B")));
+        assert_eq!(favorites.hidden, [route("A", "B")]);
+        favorites.toggle_pin(route("A", "B"));
+        favorites.toggle_pin(route("A", "B"));
+        assert!(favorites.pinned.is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+use gloo::file::{Blob, BlobContents, ObjectUrl};
+use leptos::*;
+
+/// Lets the browser save `content` as a file called `filename`.
+pub fn download(filename: &str, mime: &str, content: impl BlobContents) {
+    let url = ObjectUrl::from(Blob::new_with_options(content, Some(mime)));
+    let link = html::a();
+    link.set_href(&url);
+    link.set_download(filename);
+    link.click();
+    // Revoking the url right away can cancel the download in some browsers.
+    gloo::timers::callback::Timeout::new(10_000, move || drop(url)).forget();
+}
+
+/// Reads the first file picked in an `<input type="file">` as text.
+pub async fn read_picked(ev: &ev::Event) -> Option<String> {
+    let input = event_target::<web_sys::HtmlInputElement>(ev);
+    let file = gloo::file::File::from(input.files()?.get(0)?);
+    input.set_value("");
+    gloo::file::futures::read_as_text(&file).await.ok()
+}
@@ -0,0 +1,201 @@
+//! The printable körjournal, laid out as a PDF.
+
+use crate::{
+    pdf::{fit, Document, Font, Page, HEIGHT, WIDTH},
+    reimbursement::{kronor, Reimbursement},
+    Trip,
+};
+
+const MARGIN: f32 = 50.;
+const ROW: f32 = 15.;
+const SIZE: f32 = 9.;
+/// Left edges of date, route and reason, right edges of km and minutes.
+const COLUMNS: [f32; 5] = [MARGIN, 100., 300., 490., WIDTH - MARGIN];
+/// Room needed below the last row for totals and signatures.
+const FOOTER: f32 = 190.;
+
+pub struct Journal<'a> {
+    pub employee: &'a str,
+    pub vehicle: &'a str,
+    pub period: String,
+    pub trips: &'a [Trip],
+    pub reimbursement: Reimbursement,
+    /// Opening and closing odometer readings, only when the journal covers a single vehicle.
+    pub readings: Option<(u32, u32)>,
+    /// Printed below the totals, like private driving between trips.
+    pub notes: &'a [String],
+}
+
+impl Journal<'_> {
+    fn header(&self, page: &mut Page) -> f32 {
+        let mut y = HEIGHT - MARGIN - 10.;
+        page.text(MARGIN, y, 18., Font::Bold, "Körjournal");
+        y -= 28.;
+        let readings = self
+            .readings
+            .map(|(opening, closing)| format!("{opening} – {closing} km"))
+            .unwrap_or_default();
+        for (label, value) in [
+            ("Namn", self.employee),
+            ("Fordon", self.vehicle),
+            ("Period", &self.period),
+            ("Mätare", &readings),
+        ] {
+            page.text(MARGIN, y, 10., Font::Bold, label);
+            page.text(MARGIN + 60., y, 10., Font::Regular, value);
+            y -= 14.;
+        }
+        y -= 14.;
+        page.text(COLUMNS[0], y, SIZE, Font::Bold, "Datum");
+        page.text(COLUMNS[1], y, SIZE, Font::Bold, "Från–till");
+        page.text(COLUMNS[2], y, SIZE, Font::Bold, "Ärende");
+        page.text_right(COLUMNS[3], y, SIZE, Font::Bold, "Km");
+        page.text_right(COLUMNS[4], y, SIZE, Font::Bold, "Min");
+        page.line(MARGIN, y - 4., WIDTH - MARGIN, y - 4.);
+        y - ROW
+    }
+
+    fn row(&self, page: &mut Page, y: f32, trip: &Trip) {
+        let km = format!("{:.1}", trip.calculate_distance()).replace('.', ",");
+        page.text(
+            COLUMNS[0],
+            y,
+            SIZE,
+            Font::Regular,
+            &trip.date.format("%Y-%m-%d").to_string(),
+        );
+        let route = fit(&trip.from_to(), COLUMNS[2] - COLUMNS[1] - 8., SIZE);
+        page.text(COLUMNS[1], y, SIZE, Font::Regular, &route);
+        let reason = fit(&trip.reason, COLUMNS[3] - COLUMNS[2] - 40., SIZE);
+        page.text(COLUMNS[2], y, SIZE, Font::Regular, &reason);
+        page.text_right(COLUMNS[3], y, SIZE, Font::Regular, &km);
+        page.text_right(
+            COLUMNS[4],
+            y,
+            SIZE,
+            Font::Regular,
+            &trip.calculate_time().to_string(),
+        );
+    }
+
+    fn footer(&self, page: &mut Page, mut y: f32) {
+        let distance: f32 = self.trips.iter().map(|t| t.calculate_distance()).sum();
+        let time: u32 = self.trips.iter().map(|t| t.calculate_time()).sum();
+        page.line(MARGIN, y + ROW - 4., WIDTH - MARGIN, y + ROW - 4.);
+        page.text(COLUMNS[0], y, SIZE, Font::Bold, "Summa");
+        page.text_right(
+            COLUMNS[3],
+            y,
+            SIZE,
+            Font::Bold,
+            &format!("{distance:.1}").replace('.', ","),
+        );
+        page.text_right(COLUMNS[4], y, SIZE, Font::Bold, &time.to_string());
+        y -= 2. * ROW;
+        for (label, amount) in [
+            ("Skattefri milersättning", self.reimbursement.tax_free),
+            ("Skattepliktig milersättning", self.reimbursement.taxable),
+            ("Total milersättning", self.reimbursement.total()),
+        ] {
+            page.text(MARGIN, y, 10., Font::Regular, label);
+            page.text_right(COLUMNS[4], y, 10., Font::Regular, &kronor(amount));
+            y -= 14.;
+        }
+        for note in self.notes {
+            page.text(MARGIN, y, 10., Font::Regular, note);
+            y -= 14.;
+        }
+        y -= 50.;
+        page.line(MARGIN, y, MARGIN + 150., y);
+        page.line(MARGIN + 190., y, WIDTH - MARGIN, y);
+        page.text(MARGIN, y - 12., 8., Font::Regular, "Datum");
+        page.text(MARGIN + 190., y - 12., 8., Font::Regular, "Underskrift");
+    }
+
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut document = Document::new();
+        let mut page = Page::default();
+        let mut y = self.header(&mut page);
+        for trip in self.trips {
+            if y < MARGIN {
+                document.push(std::mem::take(&mut page));
+                y = self.header(&mut page);
+            }
+            self.row(&mut page, y, trip);
+            y -= ROW;
+        }
+        if y < FOOTER + 14. * self.notes.len() as f32 {
+            document.push(std::mem::take(&mut page));
+            y = self.header(&mut page);
+        }
+        self.footer(&mut page, y);
+        document.push(page);
+        document.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_trip;
+
+    fn count(pdf: &[u8], needle: &[u8]) -> usize {
+        pdf.windows(needle.len()).filter(|w| *w == needle).count()
+    }
+
+    fn journal(trips: &[Trip]) -> Vec<u8> {
+        Journal {
+            employee: "Kim",
+            vehicle: "ABC123",
+            period: "2024-03".into(),
+            trips,
+            reimbursement: Reimbursement::default(),
+            readings: Some((1200, 1260)),
+            notes: &[],
+        }
+        .to_pdf()
+    }
+
+    fn pages(pdf: &[u8]) -> usize {
+        count(pdf, b"/Type /Page /Parent")
+    }
+
+    #[test]
+    fn a_short_month_fits_on_one_page() {
+        let pdf = journal(&vec![test_trip(); 3]);
+        assert_eq!(pages(&pdf), 1);
+        assert_eq!(count(&pdf, b"(2024-03-01)"), 3);
+        assert_eq!(count(&pdf, b"(1200 \x96 1260 km)"), 1);
+    }
+
+    #[test]
+    fn rows_continue_on_new_pages_under_a_new_header() {
+        let pdf = journal(&vec![test_trip(); 100]);
+        assert_eq!(pages(&pdf), 3);
+        assert_eq!(count(&pdf, b"(K\xf6rjournal)"), 3);
+        assert_eq!(count(&pdf, b"(2024-03-01)"), 100);
+        assert_eq!(count(&pdf, b"(Summa)"), 1);
+    }
+
+    #[test]
+    fn the_totals_move_to_a_page_of_their_own_when_out_of_room() {
+        // Exactly a page of rows, leaving no room below for the totals and signatures.
+        let pdf = journal(&vec![test_trip(); 42]);
+        assert_eq!(pages(&pdf), 2);
+        assert_eq!(count(&pdf, b"(2024-03-01)"), 42);
+        assert_eq!(count(&pdf, b"(Summa)"), 1);
+    }
+
+    #[test]
+    fn long_routes_and_reasons_are_cut_to_their_columns() {
+        let trip = Trip {
+            to: "Carlsund utbildningscentrum".into(),
+            stops: vec!["Motala idrottsgymnasium".into()],
+            reason: "Studiedag om digitala verktyg i undervisningen".into(),
+            ..test_trip()
+        };
+        let pdf = journal(&[trip]);
+        assert_eq!(count(&pdf, b"...)"), 2);
+        assert_eq!(count(&pdf, b"undervisningen"), 0);
+    }
+}
@@ -0,0 +1,120 @@
+//! Deleted trips, kept around for a while so that a deletion can be undone.
+
+use chrono::{Duration, NaiveDateTime};
+use leptos::*;
+use leptos_use::{storage::use_local_storage, utils::JsonCodec};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Trip, Trips};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Deleted {
+    pub trip: Trip,
+    pub deleted: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Killring {
+    /// Most recently deleted first.
+    deleted: Vec<Deleted>,
+}
+
+impl Killring {
+    pub fn all(&self) -> &[Deleted] {
+        &self.deleted
+    }
+    pub fn add(&mut self, trip: Trip, deleted: NaiveDateTime) {
+        self.purge(&trip.uuid);
+        self.deleted.insert(0, Deleted { trip, deleted });
+    }
+    pub fn contains(&self, trip: &Uuid) -> bool {
+        self.deleted.iter().any(|d| d.trip.uuid == *trip)
+    }
+    /// Takes the trip out of the trash, to be put back among the trips.
+    pub fn restore(&mut self, trip: &Uuid) -> Option<Trip> {
+        let index = self.deleted.iter().position(|d| d.trip.uuid == *trip)?;
+        Some(self.deleted.remove(index).trip)
+    }
+    pub fn purge(&mut self, trip: &Uuid) {
+        self.deleted.retain(|d| d.trip.uuid != *trip);
+    }
+    /// Adds what `other` deleted, unless it is already here or back among `trips`.
+    pub fn merge(&mut self, other: Killring, trips: &Trips) {
+        for deleted in other.deleted {
+            if !self.contains(&deleted.trip.uuid) && !trips.contains(&deleted.trip.uuid) {
+                self.deleted.push(deleted);
+            }
+        }
+        self.deleted.sort_by_key(|d| std::cmp::Reverse(d.deleted));
+    }
+    pub fn clear(&mut self) {
+        self.deleted.clear();
+    }
+    /// Drops what was deleted more than `days` ago, returning whether anything was dropped.
+    pub fn expire(&mut self, now: NaiveDateTime, days: u32) -> bool {
+        let before = self.deleted.len();
+        let limit = now - Duration::days(days.into());
+        self.deleted.retain(|d| d.deleted >= limit);
+        before != self.deleted.len()
+    }
+}
+
+pub fn use_killring() -> (Signal<Killring>, WriteSignal<Killring>) {
+    let (r_killring, w_killring, _) = use_local_storage::<Killring, JsonCodec>("my-trash");
+    (r_killring, w_killring)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::test_trip;
+
+    fn at(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, day)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn deleting_again_moves_the_trip_to_the_front() {
+        let (first, second) = (test_trip(), test_trip());
+        let mut ring = Killring::default();
+        ring.add(first.clone(), at(1));
+        ring.add(second.clone(), at(2));
+        ring.add(first.clone(), at(3));
+        let order: Vec<_> = ring
+            .all()
+            .iter()
+            .map(|d| (d.trip.uuid, d.deleted))
+            .collect();
+        assert_eq!(order, [(first.uuid, at(3)), (second.uuid, at(2))]);
+        ring.purge(&first.uuid);
+        assert!(!ring.contains(&first.uuid) && ring.contains(&second.uuid));
+    }
+
+    #[test]
+    fn restoring_takes_the_trip_out_of_the_trash() {
+        let trip = test_trip();
+        let mut ring = Killring::default();
+        ring.add(trip.clone(), at(1));
+        assert_eq!(ring.restore(&trip.uuid), Some(trip.clone()));
+        assert!(ring.all().is_empty());
+        assert_eq!(ring.restore(&trip.uuid), None);
+    }
+
+    #[test]
+    fn only_what_is_older_than_the_retention_expires() {
+        let (old, recent) = (test_trip(), test_trip());
+        let mut ring = Killring::default();
+        ring.add(old.clone(), at(1));
+        ring.add(recent.clone(), at(2));
+        assert!(!ring.expire(at(31), 30));
+        assert!(ring.expire(at(31), 29));
+        assert!(!ring.contains(&old.uuid) && ring.contains(&recent.uuid));
+        assert!(!ring.expire(at(31), 29));
+    }
+}
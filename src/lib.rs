@@ -1,28 +1,60 @@
 #![feature(iter_map_windows)]
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::HashMap;
 
-use chrono::NaiveDate;
+use itertools::Itertools;
+
+use chrono::{NaiveDate, NaiveDateTime};
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
-use pages::checkpoint::Month;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{
+    category::Category, cost_centres::CostCentres, favorites::Favorites, odometer::Odometer,
+    vehicles::Vehicles,
+};
+
 // Modules
+mod backup;
+mod category;
 mod components;
+mod cost_centres;
+mod csv;
 mod destinations;
+mod favorites;
+mod files;
+mod journal;
+mod killring;
+mod odometer;
+mod offline;
 mod pages;
+mod pdf;
+mod period;
+mod reimbursement;
+mod report;
+mod settings;
+mod statistics;
+mod storage;
+pub mod sync;
+mod vehicles;
 
-use crate::pages::checkpoint::{CheckpointSummary, Checkpoints, Report};
+use crate::pages::checkpoint::{CheckpointSummary, Checkpoints, Report, Trash};
 // Top-Level pages
+use crate::offline::UpdateReady;
+use crate::pages::dashboard::Dashboard;
+use crate::pages::data::Data;
 use crate::pages::home::Home;
-use crate::pages::not_found::NotFound;
+use crate::pages::places::Places;
+use crate::pages::settings::SettingsPage;
+use crate::storage::StorageProblem;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 struct Trips {
     trips: Vec<Trip>,
+    vehicles: Vehicles,
+    cost_centres: CostCentres,
 }
 
 impl Trips {
@@ -33,20 +65,91 @@ impl Trips {
     fn remove(&mut self, uuid: &Uuid) {
         self.trips.retain(|x| x.uuid != *uuid);
     }
-    fn favorites(&self) -> Vec<Trip> {
+    /// Replaces the trip with the same `uuid`, keeping the list sorted by date.
+    fn update(&mut self, trip: Trip) {
+        self.remove(&trip.uuid);
+        self.add(trip);
+    }
+    /// Swaps in another set of trips, like the merged result of a sync.
+    fn replace(&mut self, trips: Vec<Trip>) {
+        self.trips = trips;
+        self.trips.sort_by_cached_key(|k| k.date);
+    }
+    fn contains(&self, uuid: &Uuid) -> bool {
+        self.trips.iter().any(|x| x.uuid == *uuid)
+    }
+    /// Adds the trips that are not already known by `uuid`, returning how many were added.
+    fn merge(&mut self, trips: impl IntoIterator<Item = Trip>) -> usize {
+        let mut added = 0;
+        for trip in trips {
+            if !self.contains(&trip.uuid) {
+                self.add(trip);
+                added += 1;
+            }
+        }
+        added
+    }
+    /// Calls `old` by its new name in every trip that passes it, returning how many changed.
+    fn rename_place(&mut self, old: &str, new: &str, now: NaiveDateTime) -> usize {
+        let mut changed = 0;
+        for trip in self
+            .trips
+            .iter_mut()
+            .filter(|t| t.route().any(|p| p == old))
+        {
+            for place in std::iter::once(&mut trip.from)
+                .chain(&mut trip.stops)
+                .chain(std::iter::once(&mut trip.to))
+            {
+                if place == old {
+                    *place = new.to_owned();
+                }
+            }
+            trip.edited = Some(now);
+            changed += 1;
+        }
+        changed
+    }
+    /// How many trips start, stop or end at each place.
+    fn place_counts(&self) -> HashMap<&str, usize> {
         let mut counts = HashMap::new();
-        for t in self.trips.iter() {
-            *counts.entry((&t.from, &t.to)).or_insert(0_usize) += 1;
+        for place in self.trips.iter().flat_map(|t| t.route().unique()) {
+            *counts.entry(place.as_str()).or_insert(0) += 1;
         }
-        let mut counts: Vec<_> = counts.into_iter().collect();
-        counts.sort_by(|a, b| b.1.cmp(&a.1));
-        let mut finish = Vec::new();
-        for ((f, t), _) in counts.iter().take(5) {
-            if let Some(found) = self.trips.iter().find(|x| x.from == **f && x.to == **t) {
-                finish.push(found.clone());
+        counts
+    }
+    fn favorites(&self, preferences: &Favorites, today: NaiveDate) -> Vec<Trip> {
+        preferences
+            .rank(&self.trips, today)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
+    /// Reasons given on the trips `keep` lets through, the most used first and ties broken by
+    /// which was used last.
+    fn ranked_reasons(&self, keep: impl Fn(&Trip) -> bool) -> Vec<String> {
+        let mut counts: HashMap<&str, (usize, usize)> = HashMap::new();
+        for (i, t) in self.trips.iter().enumerate().filter(|(_, t)| keep(t)) {
+            let reason = t.reason.trim();
+            if !reason.is_empty() {
+                let (count, last) = counts.entry(reason).or_default();
+                *count += 1;
+                *last = i;
             }
         }
-        finish
+        counts
+            .into_iter()
+            .sorted_by(|a, b| b.1.cmp(&a.1))
+            .map(|(reason, _)| reason.to_owned())
+            .collect()
+    }
+    /// Every reason used so far, for the datalist.
+    fn reasons(&self) -> Vec<String> {
+        self.ranked_reasons(|_| true)
+    }
+    /// What was usually the errand when going from `from` to `to`.
+    fn reasons_between(&self, from: &str, to: &str) -> Vec<String> {
+        self.ranked_reasons(|t| t.from == from && t.to == to)
     }
     fn recent(&self, n: usize) -> Vec<Trip> {
         self.trips.iter().rev().take(n).cloned().collect()
@@ -55,21 +158,48 @@ impl Trips {
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 struct Trip {
+    /// Defaulted so that a trip can be read straight from the form, which has no id.
     #[serde(default = "Uuid::new_v4")]
     uuid: Uuid,
     date: NaiveDate,
     from: String,
+    /// Places visited on the way, in order, for a rundresa.
+    #[serde(default)]
+    stops: Vec<String>,
     to: String,
     distance: f32,
     time: u32,
     reason: String,
+    /// Defaulted so that trips added before categories count as tjänsteresor.
+    #[serde(default)]
+    category: Category,
+    /// An unchecked checkbox is left out of the form data.
     #[serde(default)]
     returning: bool,
+    /// Only set in odometer mode, where `distance` is derived from the readings.
+    odometer: Option<Odometer>,
+    vehicle: Option<Uuid>,
+    /// The code in [`CostCentres`] the trip is billed to.
+    cost_centre: Option<String>,
+    /// When the trip was last changed after it was first logged.
+    edited: Option<NaiveDateTime>,
 }
 
 impl Trip {
+    /// Whether the trips are the same drive, regardless of when or where they were entered.
+    fn same_journey(&self, other: &Trip) -> bool {
+        self.date == other.date && self.route().eq(other.route())
+    }
+    /// Every place in order, from the start through the stops to the destination.
+    fn route(&self) -> impl Iterator<Item = &String> + Clone {
+        std::iter::once(&self.from)
+            .chain(&self.stops)
+            .chain(std::iter::once(&self.to))
+    }
     fn calculate_distance(&self) -> f32 {
-        if self.returning {
+        if let Some(odometer) = self.odometer {
+            odometer.distance() as f32
+        } else if self.returning {
             self.distance * 2.
         } else {
             self.distance
@@ -82,30 +212,40 @@ impl Trip {
             self.time
         }
     }
+    fn summary(&self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.date.format("%Y-%m-%d"),
+            self.from_to(),
+            self.distance_for_human(),
+            self.reason
+        )
+    }
     fn distance_for_human(&self) -> String {
         format!("{} km", self.calculate_distance()).replace('.', ",")
     }
+    #[allow(clippy::wrong_self_convention)]
     fn from_to(&self) -> String {
         format!(
-            "{}-{}{}",
-            self.from,
-            self.to,
+            "{}{}",
+            self.route().join("-"),
             if self.returning { " ToR" } else { "" }
         )
     }
-    fn report_row(&self, longest_trip: usize, longest_distance: usize) -> String {
-        let Self {
-            date,
-            reason,
-            
-            ..
-        } = &self;
-        let points = self.from_to();
-        let date = date.format("%d/%m").to_string();
-        let distance = self.distance_for_human();
-        let p_1 = ".".repeat(3 + longest_trip - points.chars().count());
-        let p_2 = ".".repeat(3 + longest_distance - distance.chars().count());
-        format!("{date}: {points}{p_1}{distance}{p_2}{reason}")
+}
+
+/// A short tjänsteresa for tests to adjust with struct update syntax.
+#[cfg(test)]
+pub(crate) fn test_trip() -> Trip {
+    Trip {
+        uuid: Uuid::new_v4(),
+        date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        from: "Kommunhuset".into(),
+        to: "Ekenässkolan".into(),
+        distance: 3.2,
+        time: 7,
+        reason: "Möte".into(),
+        ..Default::default()
     }
 }
 
@@ -114,6 +254,10 @@ impl Trip {
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
+    provide_context(StorageProblem(create_rw_signal(None)));
+    let update = UpdateReady(create_rw_signal(None));
+    provide_context(update);
+    offline::register(update);
 
     view! {
         <Html lang="sv" dir="ltr" attr:data-theme="light"/>
@@ -127,12 +271,22 @@ pub fn App() -> impl IntoView {
 
         <Router>
             <Nav/>
+            <StorageWarning/>
+            <UpdatePrompt/>
             <Routes base=String::from("/abasku")>
                 <Route path="" view=Home/>
                 <Route path="checkpoint" view=Checkpoints>
                     <Route path="" view=CheckpointSummary/>
+                    <Route path="report/:year" view=Report/>
                     <Route path="report/:year/:month" view=Report/>
+                    <Route path="report/:year/quarter/:quarter" view=Report/>
+                    <Route path="report/range/:from/:to" view=Report/>
+                    <Route path="trash" view=Trash/>
                 </Route>
+                <Route path="statistics" view=Dashboard/>
+                <Route path="data" view=Data/>
+                <Route path="places" view=Places/>
+                <Route path="settings" view=SettingsPage/>
                 <Route path="/*" view=|| view!{<Redirect path="/abasku" />}/>
             </Routes>
         </Router>
@@ -153,8 +307,172 @@ pub fn Nav() -> impl IntoView {
                     <li>
                         <A href="/abasku/checkpoint">Avstämning</A>
                     </li>
+                    <li>
+                        <A href="/abasku/statistics">Statistik</A>
+                    </li>
+                    <li>
+                        <A href="/abasku/places">Platser</A>
+                    </li>
+                    <li>
+                        <A href="/abasku/data">Data</A>
+                    </li>
+                    <li>
+                        <A href="/abasku/settings">Inställningar</A>
+                    </li>
                 </ul>
             </div>
         </nav>
     }
 }
+
+/// Tells the user that their stored trips could not be read and are left untouched.
+#[component]
+pub fn StorageWarning() -> impl IntoView {
+    let StorageProblem(problem) = expect_context::<StorageProblem>();
+    view! {
+        <Show when=move || problem.with(Option::is_some)>
+            <div role="alert" class="alert alert-error rounded-none">
+                <span>
+                    {move || problem().unwrap_or_default()}
+                    ". Inga ändringar sparas. En kopia finns i webbläsaren under nyckeln "
+                    {storage::QUARANTINE_KEY} "."
+                </span>
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+pub fn UpdatePrompt() -> impl IntoView {
+    let UpdateReady(ready) = expect_context::<UpdateReady>();
+    view! {
+        <Show when=move || ready.with(Option::is_some)>
+            <div role="alert" class="alert alert-info rounded-none">
+                <span>"Ny version finns — ladda om för att använda den."</span>
+                <button
+                    class="btn btn-sm btn-primary"
+                    on:click=move |_| {
+                        if let Some(worker) = ready.get_untracked() {
+                            offline::activate(&worker);
+                        }
+                    }
+                >
+
+                    Ladda om
+                </button>
+            </div>
+        </Show>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trip(from: &str, to: &str, reason: &str) -> Trip {
+        Trip {
+            from: from.into(),
+            to: to.into(),
+            reason: reason.into(),
+            ..test_trip()
+        }
+    }
+
+    fn trips(trips: Vec<Trip>) -> Trips {
+        Trips {
+            trips,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reasons_are_ranked_by_use_and_then_by_last_use() {
+        let trips = trips(vec![
+            trip("A", "B", "Kurs"),
+            trip("A", "B", "Möte"),
+            trip("A", "B", "Handledning"),
+            trip("A", "B", " Möte "),
+            trip("A", "B", "Kurs"),
+            trip("A", "B", "Handledning"),
+            trip("A", "B", "Lunch"),
+            trip("A", "B", "  "),
+        ]);
+        // Three reasons are used twice each, Handledning most lately, then Kurs and then Möte.
+        assert_eq!(trips.reasons(), ["Handledning", "Kurs", "Möte", "Lunch"]);
+    }
+
+    #[test]
+    fn reasons_between_only_count_that_route() {
+        let trips = trips(vec![
+            trip("Kommunhuset", "Skolan", "Möte"),
+            trip("Kommunhuset", "Biblioteket", "Kurs"),
+            trip("Kommunhuset", "Biblioteket", "Kurs"),
+            trip("Skolan", "Kommunhuset", "Lunch"),
+            trip("Kommunhuset", "Skolan", "Handledning"),
+        ]);
+        assert_eq!(
+            trips.reasons_between("Kommunhuset", "Skolan"),
+            ["Handledning", "Möte"]
+        );
+        assert!(trips.reasons_between("Skolan", "Biblioteket").is_empty());
+    }
+
+    #[test]
+    fn an_edited_date_moves_the_trip_into_its_new_month() {
+        let day = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+        let mut trips = trips(vec![]);
+        for date in [day(3, 1), day(3, 20), day(4, 10)] {
+            trips.add(Trip {
+                date,
+                ..test_trip()
+            });
+        }
+        let edited = Trip {
+            date: day(4, 25),
+            ..trips.trips[0].clone()
+        };
+        trips.update(edited.clone());
+        let dates = trips.trips.iter().map(|t| t.date).collect_vec();
+        assert_eq!(dates, [day(3, 20), day(4, 10), day(4, 25)]);
+        assert_eq!(trips.trips[2], edited);
+
+        trips.update(Trip {
+            date: day(2, 28),
+            ..edited
+        });
+        let dates = trips.trips.iter().map(|t| t.date).collect_vec();
+        assert_eq!(dates, [day(2, 28), day(3, 20), day(4, 10)]);
+    }
+
+    #[test]
+    fn renaming_a_place_touches_every_trip_through_it_and_no_other() {
+        let now = NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        let mut trips = trips(vec![
+            trip("Skolan", "Kommunhuset", "Möte"),
+            Trip {
+                stops: vec!["Skolan".into()],
+                ..trip("Kommunhuset", "Biblioteket", "Kurs")
+            },
+            trip("Kommunhuset", "Biblioteket", "Kurs"),
+        ]);
+        assert_eq!(trips.rename_place("Skolan", "Ekenässkolan", now), 2);
+        let routes = trips
+            .trips
+            .iter()
+            .map(|t| t.route().join("-"))
+            .collect_vec();
+        assert_eq!(
+            routes,
+            [
+                "Ekenässkolan-Kommunhuset",
+                "Kommunhuset-Ekenässkolan-Biblioteket",
+                "Kommunhuset-Biblioteket",
+            ]
+        );
+        let edited = trips.trips.iter().map(|t| t.edited).collect_vec();
+        assert_eq!(edited, [Some(now), Some(now), None]);
+    }
+}
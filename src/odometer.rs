@@ -0,0 +1,184 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Trip;
+
+/// Mätarställning in whole kilometres at the start and end of a trip.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Odometer {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Odometer {
+    pub fn new(start: u32, end: u32) -> Option<Self> {
+        (end > start).then_some(Self { start, end })
+    }
+    /// The distance of the whole trip, both ways if it was a return trip.
+    pub fn distance(&self) -> u32 {
+        self.end - self.start
+    }
+}
+
+/// Kilometres on the odometer between two logged trips, which must have been private driving.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    pub vehicle: Option<Uuid>,
+    pub after: NaiveDate,
+    pub before: NaiveDate,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Gap {
+    pub fn distance(&self) -> u32 {
+        self.end - self.start
+    }
+    pub fn describe(&self) -> String {
+        format!(
+            "Privat körning {}–{}: {} km ({}–{})",
+            self.after.format("%d/%m"),
+            self.before.format("%d/%m"),
+            self.distance(),
+            self.start,
+            self.end
+        )
+    }
+}
+
+/// Finds gaps between consecutive trips with odometer readings on the same vehicle, in date order.
+pub fn gaps<'a>(trips: impl IntoIterator<Item = &'a Trip>) -> Vec<Gap> {
+    let mut read: Vec<(Option<Uuid>, NaiveDate, Odometer)> = trips
+        .into_iter()
+        .filter_map(|t| Some((t.vehicle, t.date, t.odometer?)))
+        .collect();
+    read.sort_by_key(|(vehicle, date, o)| (*vehicle, *date, o.start));
+    let mut gaps: Vec<Gap> = read
+        .windows(2)
+        .filter(|w| w[0].0 == w[1].0 && w[1].2.start > w[0].2.end)
+        .map(|w| Gap {
+            vehicle: w[0].0,
+            after: w[0].1,
+            before: w[1].1,
+            start: w[0].2.end,
+            end: w[1].2.start,
+        })
+        .collect();
+    gaps.sort_by_key(|g| g.before);
+    gaps
+}
+
+/// The first and last reading among `trips` for each vehicle, since readings from different
+/// cars say nothing together. Vehicles come in the order their first trip does.
+pub fn readings<'a>(trips: impl IntoIterator<Item = &'a Trip>) -> Vec<(Option<Uuid>, u32, u32)> {
+    let mut readings: Vec<(Option<Uuid>, u32, u32)> = Vec::new();
+    for (vehicle, o) in trips
+        .into_iter()
+        .filter_map(|t| Some((t.vehicle, t.odometer?)))
+    {
+        match readings.iter_mut().find(|(v, _, _)| *v == vehicle) {
+            Some((_, first, last)) => {
+                *first = (*first).min(o.start);
+                *last = (*last).max(o.end);
+            }
+            None => readings.push((vehicle, o.start, o.end)),
+        }
+    }
+    readings
+}
+
+/// Where the next trip should start: the highest reading logged so far.
+pub fn last_reading<'a>(trips: impl IntoIterator<Item = &'a Trip>) -> Option<u32> {
+    trips
+        .into_iter()
+        .filter_map(|t| t.odometer)
+        .map(|o| o.end)
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_trip;
+
+    fn trip(vehicle: u128, day: u32, start: u32, end: u32) -> Trip {
+        Trip {
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            distance: (end - start) as f32,
+            vehicle: (vehicle != 0).then(|| Uuid::from_u128(vehicle)),
+            odometer: Odometer::new(start, end),
+            ..test_trip()
+        }
+    }
+
+    #[test]
+    fn refuses_readings_that_do_not_go_forward() {
+        assert_eq!(Odometer::new(100, 100), None);
+        assert_eq!(Odometer::new(100, 90), None);
+        assert_eq!(Odometer::new(100, 112).map(|o| o.distance()), Some(12));
+    }
+
+    #[test]
+    fn finds_gaps_between_trips_in_date_order_whatever_the_input_order() {
+        let trips = [
+            trip(1, 10, 140, 150),
+            trip(1, 1, 100, 110),
+            trip(1, 5, 110, 130),
+        ];
+        let gaps = gaps(&trips);
+        assert_eq!(gaps.len(), 1);
+        let gap = &gaps[0];
+        assert_eq!((gap.start, gap.end, gap.distance()), (130, 140, 10));
+        assert_eq!(gap.after, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+        assert_eq!(gap.before, NaiveDate::from_ymd_opt(2024, 3, 10).unwrap());
+        assert_eq!(
+            gap.describe(),
+            "Privat körning 05/03–10/03: 10 km (130–140)"
+        );
+    }
+
+    #[test]
+    fn never_finds_gaps_between_different_vehicles() {
+        let trips = [
+            trip(1, 1, 100, 110),
+            trip(2, 2, 5000, 5010),
+            trip(1, 3, 110, 120),
+            trip(2, 4, 5020, 5030),
+            trip(0, 5, 300, 310),
+        ];
+        let gaps = gaps(&trips);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].vehicle, Some(Uuid::from_u128(2)));
+        assert_eq!((gaps[0].start, gaps[0].end), (5010, 5020));
+    }
+
+    #[test]
+    fn keeps_readings_apart_per_vehicle() {
+        let trips = [
+            trip(2, 1, 5000, 5010),
+            trip(1, 2, 110, 120),
+            trip(2, 3, 5020, 5030),
+            trip(1, 4, 100, 110),
+            Trip {
+                odometer: None,
+                ..trip(1, 5, 200, 210)
+            },
+        ];
+        assert_eq!(
+            readings(&trips),
+            [
+                (Some(Uuid::from_u128(2)), 5000, 5030),
+                (Some(Uuid::from_u128(1)), 100, 120),
+            ]
+        );
+        assert!(readings(&[]).is_empty());
+    }
+
+    #[test]
+    fn the_next_trip_starts_at_the_highest_reading() {
+        let trips = [trip(1, 3, 110, 120), trip(1, 1, 100, 110)];
+        assert_eq!(last_reading(&trips), Some(120));
+        assert_eq!(last_reading(&[]), None);
+    }
+}
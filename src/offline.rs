@@ -0,0 +1,77 @@
+//! Registers the service worker in `sw.js`, which keeps the app working offline, and notices
+//! when a new deployment has been installed next to the running one.
+
+use leptos::*;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{RegistrationOptions, ServiceWorker, ServiceWorkerRegistration, ServiceWorkerState};
+
+const SCRIPT: &str = "/abasku/sw.js";
+const SCOPE: &str = "/abasku/";
+/// How often to look for a new deployment while the app stays open.
+const UPDATE_INTERVAL_MS: u32 = 30 * 60 * 1000;
+
+/// The installed worker of a newer version, waiting for the user to reload. Provided by `App`.
+#[derive(Debug, Clone, Copy)]
+pub struct UpdateReady(pub RwSignal<Option<ServiceWorker>>);
+
+/// Whether a worker already controls the page, so that a newly installed one is an update.
+fn is_controlled() -> bool {
+    window().navigator().service_worker().controller().is_some()
+}
+
+pub fn register(ready: UpdateReady) {
+    // Service workers only exist on https and localhost.
+    if !window().is_secure_context() {
+        return;
+    }
+    let container = window().navigator().service_worker();
+    let mut options = RegistrationOptions::new();
+    options.scope(SCOPE);
+    spawn_local(async move {
+        let registration =
+            match JsFuture::from(container.register_with_options(SCRIPT, &options)).await {
+                Ok(registration) => registration.unchecked_into::<ServiceWorkerRegistration>(),
+                Err(e) => {
+                    logging::warn!("Kunde inte registrera service worker: {e:?}");
+                    return;
+                }
+            };
+        if let Some(waiting) = registration.waiting().filter(|_| is_controlled()) {
+            ready.0.set(Some(waiting));
+        }
+        let found = registration.clone();
+        let on_update_found = Closure::<dyn Fn()>::new(move || {
+            let Some(installing) = found.installing() else {
+                return;
+            };
+            let worker = installing.clone();
+            let on_state_change = Closure::<dyn Fn()>::new(move || {
+                if worker.state() == ServiceWorkerState::Installed && is_controlled() {
+                    ready.0.set(Some(worker.clone()));
+                }
+            });
+            installing.set_onstatechange(Some(on_state_change.as_ref().unchecked_ref()));
+            on_state_change.forget();
+        });
+        registration.set_onupdatefound(Some(on_update_found.as_ref().unchecked_ref()));
+        on_update_found.forget();
+        gloo::timers::callback::Interval::new(UPDATE_INTERVAL_MS, move || {
+            _ = registration.update();
+        })
+        .forget();
+    });
+}
+
+/// Lets the waiting worker take over, and reloads once it has.
+pub fn activate(worker: &ServiceWorker) {
+    let reload = Closure::<dyn Fn()>::new(|| {
+        _ = window().location().reload();
+    });
+    window()
+        .navigator()
+        .service_worker()
+        .set_oncontrollerchange(Some(reload.as_ref().unchecked_ref()));
+    reload.forget();
+    _ = worker.post_message(&JsValue::from_str("skip-waiting"));
+}
@@ -1,95 +1,79 @@
-use std::{
-    collections::{BTreeSet, HashMap},
-    num::ParseIntError,
-    string::ParseError,
-};
+use std::collections::HashMap;
 
-use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate};
 use itertools::Itertools;
 use leptos::{logging::log, *};
 use leptos_icons::Icon;
-use leptos_router::{use_params_map, Form, FromFormData, Outlet, A};
-use leptos_use::{storage::use_local_storage, use_clipboard, utils::JsonCodec, UseClipboardReturn};
-use serde::{Deserialize, Serialize};
+use leptos_router::{
+    use_location, use_navigate, use_params_map, use_query_map, Outlet, ParamsMap, A,
+};
+use leptos_use::{use_clipboard, UseClipboardReturn};
 use uuid::Uuid;
-use web_sys::SubmitEvent;
-
-use crate::{components::InputWrap, Trip, Trips};
-
-#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
-struct Killring {
-    marked: BTreeSet<Uuid>,
-}
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
-pub struct Month {
-    year: i32,
-    month: u32,
-}
-
-impl TryFrom<(&String, &String)> for Month {
-    type Error = ParseIntError;
-
-    fn try_from((y, m): (&String, &String)) -> Result<Self, Self::Error> {
-        Ok(Self::new(y.parse()?, m.parse()?))
-    }
-}
-
-impl Month {
-    fn new(year: i32, month: u32) -> Self {
-        Self { year, month }
-    }
-
-    pub fn first_of(&self) -> NaiveDate {
-        NaiveDate::from_ymd_opt(self.year, self.month, 1).unwrap()
-    }
-    pub fn last_of(&self) -> NaiveDate {
-        NaiveDate::from_ymd_opt(self.year, self.month + 1, 1)
-            .or_else(|| NaiveDate::from_ymd_opt(self.year + 1, 1, 1))
-            .unwrap()
-            .pred_opt()
-            .unwrap()
-    }
-    fn fmt_human(&self) -> String {
-        format!("{} {}", self.human_month_name(), self.year)
-    }
-    fn human_month_name(&self) -> &'static str {
-        match self.month {
-            1 => "januari",
-            2 => "februari",
-            3 => "mars",
-            4 => "april",
-            5 => "maj",
-            6 => "juni",
-            7 => "juli",
-            8 => "augusti",
-            9 => "september",
-            10 => "oktober",
-            11 => "november",
-            12 => "december",
-            _ => unreachable!(),
-        }
-    }
-}
+use crate::{
+    category::Category,
+    csv,
+    files::download,
+    journal::Journal,
+    killring::{use_killring, Killring},
+    odometer::{self, Gap},
+    pages::home::{AddTravel, DestinationDataList, ReasonDataList},
+    period::Period,
+    reimbursement::{kronor, Reimbursement},
+    report,
+    settings::use_settings,
+    storage::use_trips,
+    vehicles::Vehicles,
+    Trip, Trips,
+};
 
-impl From<(i32, u32)> for Month {
-    fn from(value: (i32, u32)) -> Self {
-        Self::new(value.0, value.1)
-    }
+/// Deletes and restores trips through the trash, provided by `Checkpoints`.
+#[derive(Debug, Clone, Copy)]
+pub struct TripActions {
+    trips: Signal<Trips>,
+    write_trips: WriteSignal<Trips>,
+    killring: Signal<Killring>,
+    write_killring: WriteSignal<Killring>,
+    /// The last deletion, which the undo toast offers to take back.
+    undo: RwSignal<Option<Uuid>>,
 }
 
-impl Killring {
+impl TripActions {
     fn new() -> Self {
+        let (trips, write_trips) = use_trips();
+        let (killring, write_killring) = use_killring();
         Self {
-            marked: BTreeSet::new(),
+            trips,
+            write_trips,
+            killring,
+            write_killring,
+            undo: create_rw_signal(None),
         }
     }
-
-    fn add(&mut self, trip: Uuid) {
-        self.marked.insert(trip);
+    fn delete(&self, uuid: Uuid) {
+        let Some(trip) = self
+            .trips
+            .with_untracked(|tr| tr.trips.iter().find(|t| t.uuid == uuid).cloned())
+        else {
+            return;
+        };
+        self.write_killring
+            .update(|k| k.add(trip, Local::now().naive_local()));
+        self.write_trips.update(|tr| tr.remove(&uuid));
+        self.undo.set(Some(uuid));
     }
-    fn contains(&self, trip: &Uuid) -> bool {
-        self.marked.contains(trip)
+    fn restore(&self, uuid: Uuid) {
+        let mut restored = None;
+        self.write_killring.update(|k| restored = k.restore(&uuid));
+        if let Some(trip) = restored {
+            self.write_trips.update(|tr| tr.add(trip));
+        }
+        if self.undo.get_untracked() == Some(uuid) {
+            self.undo.set(None);
+        }
+    }
+    fn is_deleted(&self, uuid: Uuid) -> bool {
+        self.killring.with(|k| k.contains(&uuid))
     }
 }
 
@@ -97,57 +81,211 @@ impl Killring {
 pub struct MonthStatistic {
     distance: f32,
     time: u32,
+    reimbursement: Reimbursement,
+    per_vehicle: Vec<(String, f32)>,
+    per_category: Vec<(Category, f32)>,
 }
 
 impl MonthStatistic {
-    fn new(distance: f32, time: u32) -> Self {
+    fn new(
+        distance: f32,
+        time: u32,
+        reimbursement: Reimbursement,
+        per_vehicle: Vec<(String, f32)>,
+        per_category: Vec<(Category, f32)>,
+    ) -> Self {
         Self {
             distance,
             time,
+            reimbursement,
+            per_vehicle,
+            per_category,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
-struct WEvent {
-    date: NaiveDate,
+/// Kilometres for each category that has any trips, in the order of [`Category::ALL`].
+fn category_totals(trips: &[Trip]) -> Vec<(Category, f32)> {
+    Category::ALL
+        .into_iter()
+        .filter_map(|c| {
+            let of_kind = trips.iter().filter(|t| t.category == c).collect_vec();
+            (!of_kind.is_empty()).then(|| (c, of_kind.iter().map(|t| t.calculate_distance()).sum()))
+        })
+        .collect()
 }
 
 #[component]
 pub fn Checkpoints() -> impl IntoView {
+    let (r_settings, _) = use_settings();
+    let actions = TripActions::new();
+    provide_context(actions);
+    let days = r_settings.with_untracked(|s| s.trash_days);
+    let mut killring = actions.killring.get_untracked();
+    if killring.expire(Local::now().naive_local(), days) {
+        actions.write_killring.set(killring);
+    }
     view! {
         <div class="grid min-h-svh">
             <div class="w-11/12 flex justify-center flex-col lg:flex-row items-center lg:items-start gap-12 py-12">
                 <Outlet/>
             </div>
+            <UndoToast/>
+        </div>
+    }
+}
+
+/// Offers to take back the last deletion for a few seconds.
+#[component]
+fn UndoToast() -> impl IntoView {
+    let actions = expect_context::<TripActions>();
+    create_effect(move |_| {
+        if let Some(uuid) = actions.undo.get() {
+            gloo::timers::callback::Timeout::new(8_000, move || {
+                if actions.undo.get_untracked() == Some(uuid) {
+                    actions.undo.set(None);
+                }
+            })
+            .forget();
+        }
+    });
+    view! {
+        <Show when=move || actions.undo.with(Option::is_some)>
+            <div class="toast toast-center">
+                <div class="alert shadow-lg">
+                    <span>Resan flyttades till papperskorgen</span>
+                    <button
+                        class="btn btn-sm btn-primary"
+                        on:click=move |_| {
+                            if let Some(uuid) = actions.undo.get_untracked() {
+                                actions.restore(uuid);
+                            }
+                        }
+                    >
+
+                        Ångra
+                    </button>
+                </div>
+            </div>
+        </Show>
+    }
+}
+
+#[component]
+pub fn Trash() -> impl IntoView {
+    let actions = expect_context::<TripActions>();
+    let (r_settings, _) = use_settings();
+    let deleted = Signal::derive(move || actions.killring.with(|k| k.all().to_vec()));
+    let expiry = move || {
+        format!(
+            "Resor i papperskorgen tas bort för gott efter {} dagar.",
+            r_settings.with(|s| s.trash_days)
+        )
+    };
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-xl flex flex-col gap-6">
+            <div class="flex justify-between items-center">
+                <h2 class="text-xl font-medium">Papperskorg</h2>
+                <A href="/abasku/checkpoint" class="btn btn-sm btn-ghost">
+                    Tillbaka
+                </A>
+            </div>
+            <p class="text-sm">{expiry}</p>
+            <Show
+                when=move || deleted.with(|d| !d.is_empty())
+                fallback=|| view! { <p class="text-sm text-gray-500">Papperskorgen är tom.</p> }
+            >
+                <ul role="list" class="divide-y divide-gray-100">
+                    <For each=deleted key=|d| d.trip.uuid let:item>
+                        <li class="flex justify-between items-center gap-x-6 py-4">
+                            <div class="min-w-0">
+                                <p class="text-sm font-semibold leading-6">{item.trip.summary()}</p>
+                                <p class="text-xs leading-5 text-gray-500">
+                                    "Raderad "
+                                    {item.deleted.format("%Y-%m-%d %H:%M").to_string()}
+                                </p>
+                            </div>
+                            <div class="flex gap-2">
+                                <button
+                                    class="btn btn-sm btn-outline btn-primary"
+                                    on:click=move |_| actions.restore(item.trip.uuid)
+                                >
+                                    Återställ
+                                </button>
+                                <button
+                                    class="btn btn-sm btn-outline btn-warning"
+                                    on:click=move |_| {
+                                        actions.write_killring.update(|k| k.purge(&item.trip.uuid))
+                                    }
+                                >
+
+                                    Radera
+                                </button>
+                            </div>
+                        </li>
+                    </For>
+                </ul>
+                <button
+                    class="btn btn-warning btn-outline"
+                    on:click=move |_| actions.write_killring.update(Killring::clear)
+                >
+                    Töm papperskorgen
+                </button>
+            </Show>
         </div>
     }
 }
 
 #[component]
 pub fn CheckpointSummary() -> impl IntoView {
-    let (r_trips, w_trips, _) = use_local_storage::<Trips, JsonCodec>("my-trips");
-    let kill_ring = RwSignal::new(Killring::new());
-    provide_context((w_trips, kill_ring));
-    let months: Signal<Vec<(Month, Vec<Trip>)>> = Signal::derive(move || {
+    let TripActions {
+        trips: r_trips,
+        killring,
+        ..
+    } = expect_context();
+    let (r_settings, _) = use_settings();
+    let trash = move || format!("Papperskorg ({})", killring.with(|k| k.all().len()));
+    let vehicle = create_rw_signal(None::<Uuid>);
+    let vehicles = Signal::derive(move || r_trips.with(|tr| tr.vehicles.clone()));
+    let years = Signal::derive(move || {
+        r_trips.with(|tr| {
+            let this_year = Local::now().year();
+            tr.trips
+                .iter()
+                .map(|t| t.date.year())
+                .chain(std::iter::once(this_year))
+                .unique()
+                .sorted_by(|a, b| b.cmp(a))
+                .collect_vec()
+        })
+    });
+    let months: Signal<Vec<(Period, Vec<Trip>)>> = Signal::derive(move || {
         let groups = &r_trips()
             .trips
             .into_iter()
             .rev()
+            .filter(|t| vehicle().is_none_or(|v| t.vehicle == Some(v)))
             .group_by(|t| (t.date.year(), t.date.month()));
         groups
             .into_iter()
-            .map(|(ym, tr)| (ym.into(), tr.into_iter().collect_vec()))
+            .map(|((year, month), tr)| {
+                let month = Period::Month { year, month };
+                (month, tr.into_iter().collect_vec())
+            })
             .collect_vec()
     });
-    let statistics: Signal<HashMap<Month, MonthStatistic>> = Signal::derive(move || {
-        with!(|months| {
+    let statistics: Signal<HashMap<Period, MonthStatistic>> = Signal::derive(move || {
+        with!(|months, r_settings, vehicles| {
             HashMap::from_iter(months.iter().map(|(ym, dt)| {
+                let reimbursable = dt.iter().filter(|t| vehicles.reimbursable(t));
                 (
                     ym.to_owned(),
                     MonthStatistic::new(
                         dt.iter().map(|d| d.calculate_distance()).sum(),
                         dt.iter().map(|d| d.calculate_time()).sum(),
+                        Reimbursement::for_trips(reimbursable, &r_settings.employer_rates),
+                        vehicles.totals(dt),
+                        category_totals(dt),
                     ),
                 )
             }))
@@ -155,27 +293,160 @@ pub fn CheckpointSummary() -> impl IntoView {
     });
     view! {
         <div class="w-full max-w-xl flex flex-col gap-3">
-            <For each=months key=|(ym, _)| ym.to_owned() let:iva>
-                <Interval
-                    month=iva.0.to_owned()
-                    statistics=Signal::derive(move || {
-                        statistics.with(|s| s.get(&iva.0).cloned().unwrap())
-                    })
+            <DestinationDataList/>
+            <ReasonDataList/>
+            <div class="flex justify-between items-center gap-3">
+                <VehicleFilter
+                    vehicles
+                    selected=vehicle.into()
+                    on_change=Callback::new(move |v| vehicle.set(v))
+                />
+                <A href="trash" class="btn btn-sm btn-ghost ml-auto">
+                    {trash}
+                </A>
+            </div>
+            <PeriodPicker years vehicle=vehicle.into()/>
+            {move || {
+                let vehicle = vehicle();
+                view! {
+                    <For each=months key=|(ym, _)| ym.to_owned() let:iva>
+                        <Interval
+                            period=iva.0.to_owned()
+                            trips={
+                                let period = iva.0.clone();
+                                Signal::derive(move || {
+                                    months
+                                        .with(|m| m.iter().find(|(ym, _)| *ym == period).cloned())
+                                        .map(|(_, trips)| trips)
+                                        .unwrap_or_default()
+                                })
+                            }
+
+                            statistics=Signal::derive(move || {
+                                statistics.with(|s| s.get(&iva.0).cloned().unwrap_or_default())
+                            })
+
+                            vehicles
+                            vehicle
+                        />
+                    </For>
+                }
+            }}
+        </div>
+    }
+}
 
-                    trips=iva.1
+/// Links to reports for a whole year, a quarter or any two days.
+#[component]
+pub fn PeriodPicker(years: Signal<Vec<i32>>, vehicle: Signal<Option<Uuid>>) -> impl IntoView {
+    let today = Local::now().date_naive();
+    let (r_year, w_year) = create_signal(today.year());
+    let (r_from, w_from) = create_signal(Period::Year(today.year()).first_of());
+    let (r_to, w_to) = create_signal(today);
+    let link = move |period: Period| with_vehicle(period.path(), vehicle());
+    let quarters = (1..=4)
+        .map(|quarter| {
+            let href = move || link(Period::quarter(r_year(), quarter).unwrap());
+            view! {
+                <A href=href class="btn btn-sm btn-ghost">
+                    {format!("K{quarter}")}
+                </A>
+            }
+        })
+        .collect_view();
+    let date = |ev: &ev::Event| NaiveDate::parse_from_str(&event_target_value(ev), "%Y-%m-%d").ok();
+
+    view! {
+        <div class="bg-base-200 p-4 rounded-lg flex flex-col gap-3">
+            <h2 class="font-medium">Rapport för längre period</h2>
+            <div class="flex flex-wrap items-center gap-2">
+                <select
+                    class="select select-bordered select-sm"
+                    on:change=move |ev| {
+                        if let Ok(year) = event_target_value(&ev).parse() {
+                            w_year(year);
+                        }
+                    }
+                >
+                    <For each=years key=|y| *y let:year>
+                        <option value=year selected=move || r_year() == year>
+                            {year}
+                        </option>
+                    </For>
+                </select>
+                <A href=move || link(Period::Year(r_year())) class="btn btn-sm btn-ghost">
+                    Helår
+                </A>
+                {quarters}
+            </div>
+            <div class="flex flex-wrap items-center gap-2">
+                <input
+                    type="date"
+                    class="input input-bordered input-sm"
+                    prop:value=move || r_from().to_string()
+                    on:change=move |ev| {
+                        if let Some(d) = date(&ev) {
+                            w_from(d);
+                        }
+                    }
                 />
-            </For>
+                "–"
+                <input
+                    type="date"
+                    class="input input-bordered input-sm"
+                    prop:value=move || r_to().to_string()
+                    on:change=move |ev| {
+                        if let Some(d) = date(&ev) {
+                            w_to(d);
+                        }
+                    }
+                />
+                <A
+                    href=move || link(Period::range(r_from(), r_to()))
+                    class="btn btn-sm btn-outline btn-primary"
+                >
+                    Visa rapport
+                </A>
+            </div>
         </div>
     }
 }
 
+/// Picks one vehicle to show, or all of them. Hidden until there are vehicles to pick from.
+#[component]
+pub fn VehicleFilter(
+    vehicles: Signal<Vehicles>,
+    selected: Signal<Option<Uuid>>,
+    on_change: Callback<Option<Uuid>>,
+) -> impl IntoView {
+    let options = Signal::derive(move || vehicles.with(|v| v.all().to_vec()));
+    view! {
+        <select
+            class="select select-bordered w-full max-w-xs"
+            class:hidden=move || options.with(Vec::is_empty)
+            on:change=move |ev| on_change(Uuid::parse_str(&event_target_value(&ev)).ok())
+        >
+            <option value="" selected=move || selected().is_none()>
+                Alla fordon
+            </option>
+            <For each=options key=|v| v.id let:v>
+                <option value=v.id.to_string() selected=move || selected() == Some(v.id)>
+                    {v.for_humans()}
+                </option>
+            </For>
+        </select>
+    }
+}
+
 #[component]
 pub fn Interval(
-    month: Month,
+    period: Period,
     statistics: Signal<MonthStatistic>,
-    trips: Vec<Trip>,
+    trips: Signal<Vec<Trip>>,
+    vehicles: Signal<Vehicles>,
+    vehicle: Option<Uuid>,
 ) -> impl IntoView {
-    let date_str = month.fmt_human();
+    let date_str = period.fmt_human();
     let distance = Signal::derive(move || {
         let dist = statistics.with(|s| s.distance);
         format!("{dist:.1}").replace('.', ",")
@@ -184,13 +455,50 @@ pub fn Interval(
         let tim = statistics.with(|s| s.time as f32 / 60.);
         format!("{tim:.1}").replace('.', ",")
     });
-    let trip_views = trips
-        .into_iter()
-        .map(|t| {
-            view! { <TripRow trip=t/> }
+    let reimbursement =
+        Signal::derive(move || statistics.with(|s| kronor(s.reimbursement.total())));
+    let per_vehicle = move || {
+        statistics.with(|s| {
+            (s.per_vehicle.len() > 1).then(|| {
+                s.per_vehicle
+                    .iter()
+                    .map(|(name, distance)| {
+                        let distance = format!("{distance:.1} km").replace('.', ",");
+                        view! {
+                            <li class="flex justify-between text-sm py-1">
+                                <span>{name.clone()}</span>
+                                <span>{distance}</span>
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            })
         })
-        .collect_view();
-    let href = format!("report/{}/{}", month.year, month.month);
+    };
+    let per_category = move || {
+        statistics.with(|s| {
+            (s.per_category.len() > 1).then(|| {
+                s.per_category
+                    .iter()
+                    .map(|(category, distance)| {
+                        let distance = format!("{distance:.1} km").replace('.', ",");
+                        view! {
+                            <li class="flex justify-between text-sm py-1">
+                                <span>{category.for_humans()}</span>
+                                <span>{distance}</span>
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            })
+        })
+    };
+    let csv_name = format!("resor-{}.csv", period.slug());
+    let csv_content = Signal::derive(move || with!(|trips, vehicles| csv::export(trips, vehicles)));
+    let export = move |_| {
+        csv_content.with_untracked(|c| download(&csv_name, "text/csv;charset=utf-8", c.as_str()))
+    };
+    let href = with_vehicle(period.path(), vehicle);
     view! {
         <div class="collapse bg-base-200">
             <input type="checkbox" class="h-full w-full"/>
@@ -208,6 +516,11 @@ pub fn Interval(
                         <span class="place-self-center text-sm ">{time} h</span>
                     </div>
 
+                    <div class="place-self-center flex gap-1">
+                        <Icon icon=icondata::BsCashCoin/>
+                        <span class="place-self-center text-sm ">{reimbursement}</span>
+                    </div>
+
                 </div>
             </div>
             <div class="collapse-content">
@@ -216,8 +529,15 @@ pub fn Interval(
                         <A href=href class="btn btn-sm btn-outline btn-primary">
                             Generera rapport
                         </A>
+                        <button on:click=export class="btn btn-sm btn-outline btn-primary">
+                            Exportera CSV
+                        </button>
                     </li>
-                    {trip_views}
+                    {per_category}
+                    {per_vehicle}
+                    <For each=trips key=|t| t.uuid let:trip>
+                        <TripRow trip/>
+                    </For>
                 </ul>
             </div>
         </div>
@@ -226,36 +546,61 @@ pub fn Interval(
 
 #[component]
 pub fn TripRow(trip: Trip) -> impl IntoView {
-    let icon = if trip.returning {
-        icondata::BsArrowLeftRight
-    } else {
-        icondata::BsArrowRight
-    };
-    let for_humans = trip.distance_for_human();
-    let date = trip.date.format("%d %b").to_string();
-    let (w_trips, killring) = expect_context::<(WriteSignal<Trips>, RwSignal<Killring>)>();
-    let delete = move |_| {
-        killring.update(|k| {
-            k.add(trip.uuid);
-        });
-        w_trips.update(|tr| {
-            tr.remove(&trip.uuid);
-        });
+    let actions = expect_context::<TripActions>();
+    let (r_trips, w_trips) = (actions.trips, actions.write_trips);
+    let uuid = trip.uuid;
+    // Follows edits, and keeps showing the trip as it was once it is deleted.
+    let current = Signal::derive(move || {
+        r_trips
+            .with(|tr| tr.trips.iter().find(|t| t.uuid == uuid).cloned())
+            .unwrap_or_else(|| trip.clone())
+    });
+    let editing = create_rw_signal(false);
+    let delete = move |_| actions.delete(uuid);
+    let edit_form = move || {
+        let trip = current.get_untracked();
+        view! {
+            <div class="pb-5">
+                <AddTravel
+                    trips=r_trips
+                    write_to=w_trips
+                    from=create_rw_signal(Some(trip.from.clone()))
+                    to=create_rw_signal(Some(trip.to.clone()))
+                    returning=create_rw_signal(Some(trip.returning))
+                    editing=trip
+                    on_saved=Callback::new(move |_| editing.set(false))
+                />
+                <button class="btn btn-ghost w-full mt-3" on:click=move |_| editing.set(false)>
+                    Avbryt
+                </button>
+            </div>
+        }
     };
-    view! {
-        <li
-            class="flex justify-between gap-x-6 py-5"
-            class=("opacity-20", move || killring().contains(&trip.uuid))
-        >
+    let row = move || {
+        let trip = current();
+        let icon = if trip.returning {
+            icondata::BsArrowLeftRight
+        } else {
+            icondata::BsArrowRight
+        };
+        let for_humans = trip.distance_for_human();
+        let date = trip.date.format("%d %b").to_string();
+        let edited = trip
+            .edited
+            .map(|e| format!("Ändrad {}", e.format("%Y-%m-%d %H:%M")));
+        view! {
             <div class="flex min-w-0 gap-x-4">
                 <div class="min-w-0 flex-auto">
                     <p class="text-sm font-semibold leading-6 text-gray-900 flex gap-x-2 content-center">
                         {trip.from.clone()} <Icon class="h-full place-self-center" icon=icon/>
+                        {trip.stops.iter().map(|s| s.clone() + " → ").collect::<String>()}
                         {trip.to.clone()}
                     </p>
                     <div class="flex gap-3 divide-x-2 mt-1  text-xs leading-5 text-gray-500">
-                        <p>{date}</p>
+                        <p title=edited.clone()>{date} {edited.map(|_| "*")}</p>
                         <p class="truncate">{trip.reason.clone()}</p>
+                        {(!trip.category.is_business())
+                            .then(|| view! { <p>{trip.category.for_humans()}</p> })}
                     </div>
                 </div>
             </div>
@@ -264,12 +609,19 @@ pub fn TripRow(trip: Trip) -> impl IntoView {
                     <p class="text-sm leading-6 text-gray-900">{for_humans}</p>
                     <p class="mt-1 text-xs leading-5 text-gray-500">{trip.time} min</p>
                 </div>
+                <button
+                    class="btn btn-ghost btn-circle text-primary"
+                    class=("invisible", move || actions.is_deleted(uuid))
+                    on:click=move |_| editing.set(true)
+                >
+                    <Icon class="size-6" icon=icondata::TiEdit/>
+                </button>
                 <div class="dropdown dropdown-top dropdown-left">
                     <div
                         tabindex="0"
                         role="button"
                         class="btn btn-ghost btn-circle text-secondary"
-                        class=("invisible", move || killring().contains(&trip.uuid))
+                        class=("invisible", move || actions.is_deleted(uuid))
                     >
                         <Icon class="size-6" icon=icondata::TiDeleteOutline/>
                     </div>
@@ -288,27 +640,75 @@ pub fn TripRow(trip: Trip) -> impl IntoView {
                     </ul>
                 </div>
             </div>
+        }
+    };
+    view! {
+        <li class=("opacity-20", move || actions.is_deleted(uuid))>
+            <Show
+                when=editing
+                fallback=move || view! { <div class="flex justify-between gap-x-6 py-5">{row}</div> }
+            >
+                {edit_form}
+            </Show>
         </li>
     }
 }
+/// Reads the period from whichever of the report routes matched.
+fn period_from(params: &ParamsMap) -> Option<Period> {
+    let date = |key| NaiveDate::parse_from_str(params.get(key)?, "%Y-%m-%d").ok();
+    if let (Some(from), Some(to)) = (date("from"), date("to")) {
+        return Some(Period::range(from, to));
+    }
+    let year = params.get("year")?.parse().ok()?;
+    match (params.get("quarter"), params.get("month")) {
+        (Some(quarter), _) => Period::quarter(year, quarter.parse().ok()?),
+        (None, Some(month)) => Period::month(year, month.parse().ok()?),
+        (None, None) => Some(Period::Year(year)),
+    }
+}
+
+/// Keeps the vehicle filter when moving to a report.
+fn with_vehicle(path: String, vehicle: Option<Uuid>) -> String {
+    match vehicle {
+        Some(v) => format!("{path}?fordon={v}"),
+        None => path,
+    }
+}
+
 #[component]
 pub fn Report() -> impl IntoView {
-    let (r_trips, _, _) = use_local_storage::<Trips, JsonCodec>("my-trips");
-    let checkpoints = use_params_map().get_untracked();
-    let month = checkpoints
-        .get("year")
-        .zip(checkpoints.get("month"))
-        .and_then(|ym| ym.try_into().ok())
-        .unwrap_or_else(|| {
+    let params = use_params_map();
+    let period = Signal::derive(move || {
+        params.with(period_from).unwrap_or_else(|| {
             log!("Kunde inte hitta eller konvertera");
-            let now = Local::now()
-                .date_naive()
-                .checked_sub_months(Months::new(1))
-                .unwrap();
-            Month::new(now.year(), now.month())
-        });
-    let start = month.first_of();
-    let last = month.last_of();
+            Period::last_month(Local::now().date_naive())
+        })
+    });
+    let query = use_query_map();
+    let vehicle = Signal::derive(move || {
+        query.with(|q| q.get("fordon").and_then(|v| Uuid::parse_str(v).ok()))
+    });
+    move || view! { <ReportBody period=period() vehicle=vehicle()/> }
+}
+
+#[component]
+pub fn ReportBody(period: Period, vehicle: Option<Uuid>) -> impl IntoView {
+    let (r_trips, _) = use_trips();
+    let (r_settings, _) = use_settings();
+    let vehicles = Signal::derive(move || r_trips.with(|tr| tr.vehicles.clone()));
+    let pathname = use_location().pathname;
+    let navigate = use_navigate();
+    let pick_vehicle = Callback::new(move |v: Option<Uuid>| {
+        let path = pathname.get_untracked();
+        let target = match v {
+            Some(v) => format!("{path}?fordon={v}"),
+            None => path,
+        };
+        navigate(&target, Default::default());
+    });
+    let title = period.fmt_human();
+    let start = period.first_of();
+    let last = period.last_of();
     let date_str = start.format("%Y-%m-%d").to_string();
     let end_str = last.format("%Y-%m-%d").to_string();
     let filtered: Vec<Trip> = r_trips
@@ -316,33 +716,134 @@ pub fn Report() -> impl IntoView {
         .trips
         .iter()
         .rev()
-        .filter(|&t| t.date >= start && t.date <= last)
+        .filter(|&t| period.contains(t.date))
+        .filter(|&t| vehicle.is_none_or(|v| t.vehicle == Some(v)))
+        // Private trips and commuting are neither reported nor reimbursed.
+        .filter(|&t| t.category.is_business())
         .cloned()
         .collect();
+    let gaps: Vec<String> = r_trips.with_untracked(|tr| {
+        odometer::gaps(&tr.trips)
+            .iter()
+            .filter(|g| period.contains(g.before))
+            .filter(|g| vehicle.is_none_or(|v| g.vehicle == Some(v)))
+            .map(Gap::describe)
+            .collect()
+    });
+    // Private driving moves the odometer too, so the readings are taken from every trip.
+    let readings = r_trips.with_untracked(|tr| {
+        odometer::readings(
+            tr.trips
+                .iter()
+                .filter(|&t| period.contains(t.date))
+                .filter(|&t| vehicle.is_none_or(|v| t.vehicle == Some(v))),
+        )
+    });
     let distance = filtered.iter().map(|t| t.calculate_distance()).sum::<f32>();
     let distance = format!("{distance:.1}").replace('.', ",");
-    let time: f32 = (filtered.iter().map(|t| t.time).sum::<u32>() as f32) / 60.;
+    let time: f32 = (filtered.iter().map(|t| t.calculate_time()).sum::<u32>() as f32) / 60.;
     let time = format!("{time:.1}").replace('.', ",");
-    let longest_trip = filtered
-        .iter()
-        .map(|t| t.from_to().chars().count())
-        .max()
-        .unwrap_or(0);
-    let longest_distance = filtered
+    let reimbursement = with!(|r_settings, vehicles| {
+        let reimbursable = filtered.iter().filter(|t| vehicles.reimbursable(t));
+        Reimbursement::for_trips(reimbursable, &r_settings.employer_rates)
+    });
+    let mut summary = vec![
+        format!("Totalt: {distance} km, {time} h"),
+        format!(
+            "Skattefri milersättning: {}",
+            kronor(reimbursement.tax_free)
+        ),
+        format!(
+            "Skattepliktig milersättning: {}",
+            kronor(reimbursement.taxable)
+        ),
+    ];
+    match readings.as_slice() {
+        [] => {}
+        [(_, opening, closing)] => summary.push(format!("Mätarställning: {opening} – {closing}")),
+        many => summary.extend(many.iter().map(|(vehicle, opening, closing)| {
+            let name = vehicle
+                .and_then(|id| vehicles.with_untracked(|v| v.get(&id).map(|v| v.for_humans())))
+                .unwrap_or_else(|| "Inget fordon".to_owned());
+            format!("Mätarställning {name}: {opening} – {closing}")
+        })),
+    }
+    let per_vehicle = vehicles.with_untracked(|v| v.totals(&filtered));
+    if per_vehicle.len() > 1 {
+        summary.extend(per_vehicle.iter().map(|(name, distance)| {
+            format!("{name}: {}", format!("{distance:.1} km").replace('.', ","))
+        }));
+    }
+    // Finance books each code separately, so every code gets a line of its own.
+    let per_cost_centre = with!(|r_trips, r_settings, vehicles| {
+        r_trips
+            .cost_centres
+            .split(&filtered)
+            .into_iter()
+            .map(|(code, trips)| {
+                let name = code.map_or_else(
+                    || "Utan kostnadsställe".to_owned(),
+                    |c| r_trips.cost_centres.name_of(&c),
+                );
+                let distance = trips.iter().map(|t| t.calculate_distance()).sum::<f32>();
+                let reimbursable = trips.into_iter().filter(|t| vehicles.reimbursable(t));
+                let reimbursement =
+                    Reimbursement::for_trips(reimbursable, &r_settings.employer_rates);
+                format!(
+                    "{name}: {}, {}",
+                    format!("{distance:.1} km").replace('.', ","),
+                    kronor(reimbursement.total())
+                )
+            })
+            .collect_vec()
+    });
+    if filtered.iter().any(|t| t.cost_centre.is_some()) {
+        summary.extend(per_cost_centre);
+    }
+    summary.extend(gaps.iter().cloned());
+    let csv_name = format!("resor-{}.csv", period.slug());
+    let csv_content = vehicles.with_untracked(|v| csv::export(&filtered, v));
+    let export = move |_| download(&csv_name, "text/csv;charset=utf-8", csv_content.as_str());
+    let pdf_name = format!("korjournal-{}.pdf", period.slug());
+    let vehicle_name = per_vehicle
         .iter()
-        .map(|t| t.distance_for_human().chars().count())
-        .max()
-        .unwrap_or(0);
-    let trip_views = filtered
-        .into_iter()
-        .map(|t| t.report_row(longest_trip, longest_distance));
-    let for_clipboard = trip_views.clone().collect::<Vec<String>>().join("\n");
-    let for_view = trip_views.map(|t| view! { <p>{t}</p> }).collect_view();
+        .map(|(name, _)| name.as_str())
+        .collect_vec()
+        .join(", ");
+    let pdf_content = r_settings.with_untracked(|s| {
+        Journal {
+            employee: &s.employee,
+            vehicle: &vehicle_name,
+            period: format!("{date_str} – {end_str}"),
+            trips: &filtered,
+            reimbursement,
+            readings: match readings.as_slice() {
+                [(_, opening, closing)] => Some((*opening, *closing)),
+                _ => None,
+            },
+            notes: &gaps,
+        }
+        .to_pdf()
+    });
+    let print = move |_| download(&pdf_name, "application/pdf", pdf_content.as_slice());
+    let (r_template, w_template) =
+        create_signal(r_settings.with_untracked(|s| s.report_template.clone()));
+    let templates = Signal::derive(move || r_settings.with(|s| s.templates()));
+    let rendered = Signal::derive(move || {
+        with!(|r_settings, vehicles, r_template| {
+            let context = report::Context {
+                vehicles,
+                rates: &r_settings.employer_rates,
+            };
+            context.render(&r_settings.template(r_template), &filtered, &summary)
+        })
+    });
     let UseClipboardReturn {
         is_supported, copy, ..
     } = use_clipboard();
     view! {
         <div class="bg-base-200 h-fit p-8 rounded-lg">
+            <h2 class="text-sm first-letter:uppercase mb-2">{title}</h2>
             <div class="flex justify-between">
 
                 <div class=" text-xl font-medium flex gap-3">
@@ -360,28 +861,59 @@ pub fn Report() -> impl IntoView {
                         <span class="place-self-center text-sm ">{time} h</span>
                     </div>
 
+                    <div class="place-self-center flex items-center gap-2">
+                        <Icon icon=icondata::BsCashCoin/>
+                        <span class="place-self-center text-sm ">
+                            {kronor(reimbursement.total())}
+                        </span>
+                    </div>
+
                 </div>
             </div>
             <div class="">
-                <Show when=is_supported>
-                    <button
-                        on:click={
-                            let copy = copy.clone();
-                            let to_copy = for_clipboard.clone();
-                            move |_| copy(&to_copy)
-                        }
+                <div class="flex flex-wrap gap-3 my-6">
+                    <VehicleFilter
+                        vehicles
+                        selected=Signal::derive(move || vehicle)
+                        on_change=pick_vehicle
+                    />
+                    <Show when=is_supported>
+                        <button
+                            on:click={
+                                let copy = copy.clone();
+                                move |_| copy(&rendered.get_untracked())
+                            }
 
-                        class="btn btn-sm btn-outline btn-primary my-6"
-                    >
-                        "Kopiera logg"
+                            class="btn btn-sm btn-outline btn-primary"
+                        >
+                            "Kopiera logg"
+                        </button>
+                    </Show>
+                    <button on:click=export class="btn btn-sm btn-outline btn-primary">
+                        "Exportera CSV"
                     </button>
-                </Show>
-                <ul role="list" class="font-mono">
-                    <li class="flex justify-around pb-4"></li>
-                    {for_view}
-                </ul>
+                    <button on:click=print class="btn btn-sm btn-outline btn-primary">
+                        "Ladda ner PDF"
+                    </button>
+                </div>
+                <select
+                    class="select select-bordered select-sm mb-4"
+                    on:change=move |ev| w_template(event_target_value(&ev))
+                >
+                    <For each=templates key=|t| t.name.clone() let:template>
+                        <option
+                            value=template.name.clone()
+                            selected={
+                                let name = template.name.clone();
+                                move || r_settings.with(|s| s.template(&r_template()).name == name)
+                            }
+                        >
+                            {template.name.clone()}
+                        </option>
+                    </For>
+                </select>
+                <pre class="font-mono text-sm whitespace-pre-wrap">{rendered}</pre>
             </div>
         </div>
     }
 }
-
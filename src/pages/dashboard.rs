@@ -0,0 +1,245 @@
+use chrono::{Datelike, Local};
+use itertools::Itertools;
+use leptos::*;
+
+use crate::{statistics, storage::use_trips, Trip};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "maj", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["mån", "tis", "ons", "tor", "fre", "lör", "sön"];
+
+#[component]
+pub fn Dashboard() -> impl IntoView {
+    let (r_trips, _) = use_trips();
+    let this_year = Local::now().year();
+    let years = Signal::derive(move || {
+        r_trips.with(|tr| {
+            tr.trips
+                .iter()
+                .map(|t| t.date.year())
+                .chain(std::iter::once(this_year))
+                .unique()
+                .sorted_by(|a, b| b.cmp(a))
+                .collect_vec()
+        })
+    });
+    let (r_year, w_year) = create_signal(this_year);
+    let trips = Signal::derive(move || {
+        r_trips.with(|tr| {
+            tr.trips
+                .iter()
+                .filter(|t| t.date.year() == r_year())
+                .cloned()
+                .collect_vec()
+        })
+    });
+    let months = Signal::derive(move || trips.with(|t| statistics::per_month(t, r_year())));
+    let distance = Signal::derive(move || {
+        months.with(|m| {
+            MONTHS
+                .iter()
+                .zip(m)
+                .map(|(name, (km, _))| (name.to_string(), *km))
+                .collect_vec()
+        })
+    });
+    let hours = Signal::derive(move || {
+        months.with(|m| {
+            MONTHS
+                .iter()
+                .zip(m)
+                .map(|(name, (_, h))| (name.to_string(), *h))
+                .collect_vec()
+        })
+    });
+    let weekdays = Signal::derive(move || {
+        trips.with(|t| {
+            WEEKDAYS
+                .iter()
+                .zip(statistics::per_weekday(t))
+                .map(|(name, n)| (name.to_string(), n as f32))
+                .collect_vec()
+        })
+    });
+    let routes = Signal::derive(move || trips.with(|t| statistics::top_routes(t, 8)));
+    let reasons = Signal::derive(move || trips.with(|t| statistics::top_reasons(t, 8)));
+    let average = move || {
+        trips.with(|t| {
+            statistics::average_distance(t)
+                .map(|km| format!("{km:.1} km").replace('.', ","))
+                .unwrap_or_else(|| "–".to_owned())
+        })
+    };
+    let count = move || trips.with(Vec::len);
+    let total = move || {
+        let km: f32 = trips.with(|t| t.iter().map(Trip::calculate_distance).sum());
+        format!("{km:.0} km")
+    };
+
+    view! {
+        <div class="min-h-svh py-12">
+            <div class="w-11/12 mx-auto flex flex-col gap-6">
+                <div class="flex flex-wrap items-center gap-6">
+                    <select
+                        class="select select-bordered"
+                        on:change=move |ev| {
+                            if let Ok(year) = event_target_value(&ev).parse() {
+                                w_year(year);
+                            }
+                        }
+                    >
+                        <For each=years key=|y| *y let:year>
+                            <option value=year selected=move || r_year() == year>
+                                {year}
+                            </option>
+                        </For>
+                    </select>
+                    <div class="stats bg-base-200">
+                        <div class="stat">
+                            <div class="stat-title">Resor</div>
+                            <div class="stat-value text-2xl">{count}</div>
+                        </div>
+                        <div class="stat">
+                            <div class="stat-title">Totalt</div>
+                            <div class="stat-value text-2xl">{total}</div>
+                        </div>
+                        <div class="stat">
+                            <div class="stat-title">Snitt per resa</div>
+                            <div class="stat-value text-2xl">{average}</div>
+                        </div>
+                    </div>
+                </div>
+                <div class="grid lg:grid-cols-2 gap-6">
+                    <Chart title="Kilometer per månad">
+                        <Columns bars=distance/>
+                    </Chart>
+                    <Chart title="Timmar per månad">
+                        <Columns bars=hours/>
+                    </Chart>
+                    <Chart title="Resor per veckodag">
+                        <Columns bars=weekdays/>
+                    </Chart>
+                    <Chart title="Vanligaste resorna">
+                        <Ranking items=routes/>
+                    </Chart>
+                    <Chart title="Vanligaste ärendena">
+                        <Ranking items=reasons/>
+                    </Chart>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn Chart(title: &'static str, children: Children) -> impl IntoView {
+    view! {
+        <div class="bg-base-200 p-6 rounded-lg flex flex-col gap-3">
+            <h2 class="text-lg font-medium">{title}</h2>
+            {children()}
+        </div>
+    }
+}
+
+const WIDTH: f32 = 360.;
+const HEIGHT: f32 = 160.;
+/// Room below the bars for their labels.
+const LABELS: f32 = 16.;
+
+/// Vertical bars scaled to the tallest one, with the value on top of each bar.
+#[component]
+fn Columns(bars: Signal<Vec<(String, f32)>>) -> impl IntoView {
+    let view_box = format!("0 0 {WIDTH} {}", HEIGHT + LABELS);
+    let columns = move || {
+        bars.with(|bars| {
+            let highest = bars.iter().map(|(_, v)| *v).fold(0., f32::max).max(1.);
+            let slot = WIDTH / bars.len().max(1) as f32;
+            bars.iter()
+                .enumerate()
+                .map(|(i, (label, value))| {
+                    // Keep room above the tallest bar for its value.
+                    let height = value / highest * (HEIGHT - 14.);
+                    let x = i as f32 * slot;
+                    let middle = x + slot / 2.;
+                    let shown = if *value > 0. {
+                        format!("{value:.0}")
+                    } else {
+                        String::new()
+                    };
+                    view! {
+                        <g>
+                            <rect
+                                x=x + slot * 0.15
+                                y=HEIGHT - height
+                                width=slot * 0.7
+                                height=height
+                                class="fill-primary"
+                            ></rect>
+                            <text
+                                x=middle
+                                y=HEIGHT - height - 3.
+                                text-anchor="middle"
+                                font-size="9"
+                                class="fill-current"
+                            >
+                                {shown}
+                            </text>
+                            <text
+                                x=middle
+                                y=HEIGHT + LABELS - 3.
+                                text-anchor="middle"
+                                font-size="10"
+                                class="fill-current"
+                            >
+                                {label.clone()}
+                            </text>
+                        </g>
+                    }
+                })
+                .collect_view()
+        })
+    };
+    view! {
+        <svg viewBox=view_box class="w-full" role="img">
+            {columns}
+        </svg>
+    }
+}
+
+/// Horizontal bars for the most common values, longest first.
+#[component]
+fn Ranking(items: Signal<Vec<(String, usize)>>) -> impl IntoView {
+    let rows = move || {
+        items.with(|items| {
+            let most = items.iter().map(|(_, n)| *n).max().unwrap_or(1) as f32;
+            items
+                .iter()
+                .map(|(label, n)| {
+                    let width = format!("{}%", *n as f32 / most * 100.);
+                    view! {
+                        <li class="flex flex-col gap-1 text-sm">
+                            <div class="flex justify-between">
+                                <span>{label.clone()}</span>
+                                <span>{*n}</span>
+                            </div>
+                            <svg class="w-full h-2" role="presentation">
+                                <rect width=width height="100%" rx="2" class="fill-secondary"></rect>
+                            </svg>
+                        </li>
+                    }
+                })
+                .collect_view()
+        })
+    };
+    view! {
+        <Show
+            when=move || items.with(|i| !i.is_empty())
+            fallback=|| view! { <p class="text-sm">Inga resor det här året.</p> }
+        >
+            <ul role="list" class="flex flex-col gap-2">
+                {rows}
+            </ul>
+        </Show>
+    }
+}
@@ -0,0 +1,324 @@
+use leptos::*;
+
+use crate::components::InputWrap;
+
+use crate::{
+    backup::{Backup, Preview, Restore},
+    csv::{self, RowError},
+    destinations::use_custom_trips,
+    files::{download, read_picked},
+    killring::use_killring,
+    settings::use_settings,
+    storage::use_trips,
+    sync::use_sync_state,
+    Trip,
+};
+
+#[component]
+pub fn Data() -> impl IntoView {
+    view! {
+        <div class="min-h-svh py-12">
+            <div class="w-11/12 flex flex-col lg:flex-row justify-center items-center lg:items-start gap-12">
+                <CsvTransfer/>
+                <BackupRestore/>
+                <Sync/>
+            </div>
+        </div>
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ImportResult {
+    added: usize,
+    duplicates: usize,
+    errors: Vec<RowError>,
+}
+
+#[component]
+pub fn CsvTransfer() -> impl IntoView {
+    let (r_trips, w_trips) = use_trips();
+    let result = create_rw_signal(None::<ImportResult>);
+    let export_all = move |_| {
+        let content = r_trips.with_untracked(|tr| csv::export(&tr.trips, &tr.vehicles));
+        download("resor.csv", "text/csv;charset=utf-8", content.as_str());
+    };
+    let import = move |ev: ev::Event| {
+        spawn_local(async move {
+            let Some(text) = read_picked(&ev).await else {
+                return;
+            };
+            let (trips, errors) = r_trips.with_untracked(|tr| csv::import(&text, &tr.vehicles));
+            let total = trips.len();
+            let mut added = 0;
+            w_trips.update(|tr| added = tr.merge(trips));
+            result.set(Some(ImportResult {
+                added,
+                duplicates: total - added,
+                errors,
+            }));
+        });
+    };
+    let summary = move || {
+        result().map(|r| {
+            let errors = r
+                .errors
+                .iter()
+                .map(|e| view! { <li class="text-error">{e.to_string()}</li> })
+                .collect_view();
+            view! {
+                <div class="text-sm">
+                    <p>{r.added} " resor importerade, " {r.duplicates} " fanns redan"</p>
+                    <ul role="list">{errors}</ul>
+                </div>
+            }
+        })
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">CSV</h2>
+            <button on:click=export_all class="btn btn-sm btn-outline btn-primary">
+                Exportera alla resor
+            </button>
+            <label class="form-control w-full">
+                <div class="label">
+                    <span class="label-text">Importera resor</span>
+                </div>
+                <input
+                    type="file"
+                    accept=".csv,text/csv"
+                    class="file-input file-input-bordered w-full"
+                    on:change=import
+                />
+            </label>
+            {summary}
+        </div>
+    }
+}
+
+#[component]
+pub fn BackupRestore() -> impl IntoView {
+    let (r_trips, w_trips) = use_trips();
+    let (r_custom, w_custom) = use_custom_trips();
+    let (r_settings, w_settings) = use_settings();
+    let (r_killring, w_killring) = use_killring();
+    let restore = create_rw_signal(None::<Restore>);
+    let error = create_rw_signal(None::<String>);
+    let save = move |_| {
+        let backup = Backup::new(
+            &r_trips.get_untracked(),
+            r_custom.get_untracked(),
+            r_settings.get_untracked(),
+            r_killring.get_untracked(),
+        );
+        match backup.and_then(|b| Ok((b.filename(), b.to_json()?))) {
+            Ok((name, json)) => download(&name, "application/json", json.as_str()),
+            Err(e) => error.set(Some(e)),
+        }
+    };
+    let pick = move |ev: ev::Event| {
+        spawn_local(async move {
+            let Some(text) = read_picked(&ev).await else {
+                return;
+            };
+            match Restore::parse(&text) {
+                Ok(r) => {
+                    error.set(None);
+                    restore.set(Some(r));
+                }
+                Err(e) => {
+                    restore.set(None);
+                    error.set(Some(e));
+                }
+            }
+        });
+    };
+    let merge = move |_| {
+        if let Some(r) = restore.get_untracked() {
+            let mut trips = r_trips.get_untracked();
+            let mut custom = r_custom.get_untracked();
+            let mut killring = r_killring.get_untracked();
+            r.merge_into(&mut trips, &mut custom, &mut killring);
+            w_trips.set(trips);
+            w_custom.set(custom);
+            w_killring.set(killring);
+            restore.set(None);
+        }
+    };
+    let replace = move |_| {
+        if let Some(r) = restore.get_untracked() {
+            w_trips.set(r.trips);
+            w_custom.set(r.custom_trips);
+            w_settings.set(r.settings);
+            w_killring.set(r.trash);
+            restore.set(None);
+        }
+    };
+    let preview = move || {
+        restore.with(|r| {
+            r.as_ref().map(|r| {
+                let Preview {
+                    new,
+                    changed,
+                    conflicting,
+                    unchanged,
+                } = r_trips.with(|tr| r.preview(tr));
+                let created = r.created.format("%Y-%m-%d %H:%M").to_string();
+                let trashed = r.trash.all().len();
+                let pairs = |pairs: Vec<(Trip, Trip)>| {
+                    pairs
+                        .into_iter()
+                        .map(|(ours, theirs)| {
+                            view! {
+                                <li class="py-1">
+                                    <p>"Här: " {ours.summary()}</p>
+                                    <p>"Kopian: " {theirs.summary()}</p>
+                                </li>
+                            }
+                        })
+                        .collect_view()
+                };
+                view! {
+                    <div class="flex flex-col gap-3 text-sm">
+                        <p>"Säkerhetskopia från " {created}</p>
+                        <p>{unchanged} " resor är oförändrade"</p>
+                        <p>{trashed} " resor i papperskorgen följer med"</p>
+                        <details>
+                            <summary>{new.len()} " nya resor"</summary>
+                            <ul role="list" class="divide-y divide-gray-100">
+                                {new
+                                    .iter()
+                                    .map(|t| view! { <li class="py-1">{t.summary()}</li> })
+                                    .collect_view()}
+                            </ul>
+                        </details>
+                        <details>
+                            <summary>{changed.len()} " ändrade resor"</summary>
+                            <ul role="list" class="divide-y divide-gray-100">
+                                {pairs(changed)}
+                            </ul>
+                        </details>
+                        <details>
+                            <summary>
+                                {conflicting.len()} " resor i konflikt (samma dag och sträcka)"
+                            </summary>
+                            <ul role="list" class="divide-y divide-gray-100">
+                                {pairs(conflicting)}
+                            </ul>
+                        </details>
+                        <div class="flex gap-3">
+                            <button on:click=merge class="btn btn-sm btn-outline btn-primary">
+                                Slå ihop
+                            </button>
+                            <button on:click=replace class="btn btn-sm btn-outline btn-warning">
+                                Ersätt allt
+                            </button>
+                            <button
+                                on:click=move |_| restore.set(None)
+                                class="btn btn-sm btn-ghost"
+                            >
+                                Avbryt
+                            </button>
+                        </div>
+                    </div>
+                }
+            })
+        })
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Säkerhetskopia</h2>
+            <button on:click=save class="btn btn-sm btn-outline btn-primary">
+                Ladda ner säkerhetskopia
+            </button>
+            <label class="form-control w-full">
+                <div class="label">
+                    <span class="label-text">Återställ från säkerhetskopia</span>
+                </div>
+                <input
+                    type="file"
+                    accept=".json,application/json"
+                    class="file-input file-input-bordered w-full"
+                    on:change=pick
+                />
+            </label>
+            <p class="text-sm text-error">{error}</p>
+            {preview}
+        </div>
+    }
+}
+
+#[component]
+pub fn Sync() -> impl IntoView {
+    let (r_trips, w_trips) = use_trips();
+    let (r_settings, w_settings) = use_settings();
+    let (r_state, w_state) = use_sync_state();
+    let busy = create_rw_signal(false);
+    let problem = create_rw_signal(None::<String>);
+    let sync_now = move |_| {
+        let (url, token) =
+            r_settings.with_untracked(|s| (s.sync_url.clone(), s.sync_token.clone()));
+        let before = r_trips.get_untracked();
+        let state = r_state.get_untracked();
+        busy.set(true);
+        problem.set(None);
+        spawn_local(async move {
+            match state.sync(&url, &token, &before).await {
+                // Changes made while waiting would be lost, so those have to go in the next round.
+                Ok(_) if r_trips.with_untracked(|tr| *tr != before) => {
+                    problem.set(Some("Resorna ändrades under tiden, synka igen".into()));
+                }
+                Ok(state) => {
+                    w_trips.update(|tr| state.restore(tr));
+                    w_state.set(state);
+                }
+                Err(e) => problem.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+    let status = move || {
+        problem().unwrap_or_else(|| {
+            r_state.with(|s| match s.last_synced {
+                Some(at) => format!("Senast synkad {}", at.format("%Y-%m-%d %H:%M")),
+                None => "Inte synkad än".to_owned(),
+            })
+        })
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Synkronisering</h2>
+            <p class="text-sm">
+                Håller resorna lika på flera enheter via en egen server. Den senaste ändringen av varje uppgift vinner.
+            </p>
+            <InputWrap label="Server" explanation="t.ex. http://192.168.1.10:8787">
+                <input
+                    type="url"
+                    class="input input-bordered w-full max-w-xs"
+                    prop:value=move || r_settings.with(|s| s.sync_url.clone())
+                    on:change=move |ev| w_settings.update(|s| s.sync_url = event_target_value(&ev))
+                />
+            </InputWrap>
+            <InputWrap label="Nyckel" explanation="om servern kräver en">
+                <input
+                    type="password"
+                    class="input input-bordered w-full max-w-xs"
+                    prop:value=move || r_settings.with(|s| s.sync_token.clone())
+                    on:change=move |ev| w_settings.update(|s| s.sync_token = event_target_value(&ev))
+                />
+            </InputWrap>
+            <p class="text-sm" class:text-error=move || problem.with(Option::is_some)>
+                {status}
+            </p>
+            <button
+                class="btn btn-secondary btn-outline"
+                disabled=move || busy() || r_settings.with(|s| s.sync_url.is_empty())
+                on:click=sync_now
+            >
+                {move || if busy() { "Synkar…" } else { "Synka nu" }}
+            </button>
+        </div>
+    }
+}
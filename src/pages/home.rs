@@ -1,4 +1,4 @@
-use std::{collections::HashMap, ops::Not};
+use std::ops::Not;
 
 use chrono::Local;
 
@@ -6,37 +6,27 @@ use itertools::Itertools;
 use leptos::*;
 use leptos_icons::Icon;
 use leptos_router::*;
-use leptos_use::{storage::use_local_storage, utils::JsonCodec};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use web_sys::SubmitEvent;
 
 use crate::{
+    category::Category,
     components::InputWrap,
-    destinations::{destinations, travel, Travel},
+    destinations::{
+        destinations, estimate, total, use_custom_trips, PlaceMatcher, TravelResolver, ALIASES,
+    },
+    favorites::Route,
+    odometer::{self, Odometer},
+    settings::use_settings,
+    storage::use_trips,
     Trip, Trips,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
-struct CustomTrips {
-    trips: HashMap<String, HashMap<String, Travel>>,
-}
-
-impl CustomTrips {
-    fn get(&self, from: &str, to: &str) -> Option<Travel> {
-        self.trips.get(from)?.get(to).cloned()
-    }
-    fn add(&mut self, trip: &Trip) {
-        let fr = trip.from.to_owned();
-        let to = trip.to.to_owned();
-        let tra: Travel = trip.into();
-        self.trips.entry(fr).or_default().insert(to, tra);
-    }
-}
-
 /// Default Home Page
 #[component]
 pub fn Home() -> impl IntoView {
-    let (r_trips, w_trips, _) = use_local_storage::<Trips, JsonCodec>("my-trips");
+    let (r_trips, w_trips) = use_trips();
     let from = create_rw_signal(None);
     let to = create_rw_signal(None);
     let returning = create_rw_signal(None);
@@ -47,7 +37,8 @@ pub fn Home() -> impl IntoView {
                 <QuickChoice trips=r_trips from to returning/>
                 <div class="form-control w-full max-w-sm outline my-6 p-6 outline-1 outline-primary rounded-xl h-fit">
                     <DestinationDataList/>
-                    <AddTravel write_to=w_trips from to returning/>
+                    <ReasonDataList/>
+                    <AddTravel trips=r_trips write_to=w_trips from to returning/>
                 </div>
             </div>
         </div>
@@ -68,7 +59,12 @@ pub fn QuickChoice(
     to: RwSignal<Option<String>>,
     returning: RwSignal<Option<bool>>,
 ) -> impl IntoView {
-    let no_favs = Signal::derive(move || trips.with(|tr| tr.favorites().is_empty()));
+    let (r_settings, w_settings) = use_settings();
+    let today = Local::now().date_naive();
+    let favs = Signal::derive(move || {
+        with!(|trips, r_settings| trips.favorites(&r_settings.favorites, today))
+    });
+    let no_favs = Signal::derive(move || favs.with(Vec::is_empty));
     let no_recents = Signal::derive(move || trips.with(|tr| tr.recent(5).is_empty()));
     let (_, w_from) = from.split();
     let (_, w_to) = to.split();
@@ -86,7 +82,6 @@ pub fn QuickChoice(
         w_returning(returning)
     });
 
-    let favs = Signal::derive(move || trips.with(|tr| tr.favorites()));
     let recents = Signal::derive(move || trips.with(|tr| tr.recent(5)));
     view! {
         <div
@@ -96,9 +91,50 @@ pub fn QuickChoice(
             <div class="flex flex-col gap-3">
                 <h2 class="text-2xl text-center">Favoriter</h2>
                 <div class="flex flex-col divide-y-2 ">
-                    <For each=favs key=move |t| t.uuid let:trip>
-                        <QuickChoiceRow trip on_submit/>
-                    </For>
+                    <For
+                        each=favs
+                        key=move |t| t.uuid
+                        children=move |trip| {
+                            let route = Route::of(&trip);
+                            let pinned = {
+                                let route = route.clone();
+                                Signal::derive(move || {
+                                    r_settings.with(|s| s.favorites.is_pinned(&route))
+                                })
+                            };
+                            let pin = {
+                                let route = route.clone();
+                                move |_| w_settings.update(|s| s.favorites.toggle_pin(route.clone()))
+                            };
+                            let hide = move |_| w_settings.update(|s| s.favorites.hide(route.clone()));
+                            view! {
+                                <QuickChoiceRow trip on_submit>
+                                    <button
+                                        class="btn btn-ghost btn-circle btn-sm"
+                                        title=move || if pinned() { "Lossa" } else { "Fäst" }
+                                        on:click=pin
+                                    >
+                                        {move || {
+                                            let icon = if pinned() {
+                                                icondata::BsPinAngleFill
+                                            } else {
+                                                icondata::BsPinAngle
+                                            };
+                                            view! { <Icon class="size-4" icon=icon/> }
+                                        }}
+
+                                    </button>
+                                    <button
+                                        class="btn btn-ghost btn-circle btn-sm"
+                                        title="Dölj"
+                                        on:click=hide
+                                    >
+                                        <Icon class="size-4" icon=icondata::BsEyeSlash/>
+                                    </button>
+                                </QuickChoiceRow>
+                            }
+                        }
+                    />
                 </div>
             </div>
             <div class="flex flex-col gap-3" class:hidden=no_recents>
@@ -114,7 +150,13 @@ pub fn QuickChoice(
 }
 
 #[component]
-pub fn QuickChoiceRow(trip: Trip, on_submit: Callback<SubmitEvent>) -> impl IntoView {
+pub fn QuickChoiceRow(
+    trip: Trip,
+    on_submit: Callback<SubmitEvent>,
+    /// Extra buttons shown before the one that picks the trip.
+    #[prop(optional)]
+    children: Option<Children>,
+) -> impl IntoView {
     let icon = if trip.returning {
         icondata::BsArrowLeftRight
     } else {
@@ -130,6 +172,9 @@ pub fn QuickChoiceRow(trip: Trip, on_submit: Callback<SubmitEvent>) -> impl Into
                     </p>
                 </div>
             </div>
+            <div class="flex items-center gap-1">
+                {children.map(|c| c())}
+            </div>
             <Form
                 action=""
                 on:submit=on_submit
@@ -149,46 +194,172 @@ pub fn QuickChoiceRow(trip: Trip, on_submit: Callback<SubmitEvent>) -> impl Into
 
 #[component]
 pub fn AddTravel(
+    trips: Signal<Trips>,
     write_to: WriteSignal<Trips>,
     from: RwSignal<Option<String>>,
     to: RwSignal<Option<String>>,
     returning: RwSignal<Option<bool>>,
+    /// Loads this trip into the form and saves over it instead of adding a new one.
+    #[prop(optional)]
+    editing: Option<Trip>,
+    /// Called once an edited trip has been saved.
+    #[prop(optional)]
+    on_saved: Option<Callback<()>>,
 ) -> impl IntoView {
-    let (r_custom, w_custom, _) = use_local_storage::<CustomTrips, JsonCodec>("my-custom-trips");
+    let (r_custom, w_custom) = use_custom_trips();
     let (r_from, w_from) = from.split();
     let (r_to, w_to) = to.split();
     let (r_returning, w_returning) = returning.split();
-    let (r_distance, w_distance) = create_signal(0.);
-    let (r_time, w_time) = create_signal(0);
+    let (r_distance, w_distance) = create_signal(editing.as_ref().map_or(0., |t| t.distance));
+    let (r_time, w_time) = create_signal(editing.as_ref().map_or(0, |t| t.time));
+    let (r_stops, w_stops) = create_signal(
+        editing
+            .as_ref()
+            .map(|t| t.stops.clone())
+            .unwrap_or_default(),
+    );
+    let (r_settings, _) = use_settings();
+    let odometer_mode = Signal::derive(move || r_settings.with(|s| s.odometer));
+    let vehicles = Signal::derive(move || trips.with(|tr| tr.vehicles.all().to_vec()));
+    let (r_vehicle, w_vehicle) = create_signal(editing.as_ref().map(|t| t.vehicle));
+    // Sticks to the last used vehicle unless the user picks another one.
+    let vehicle = Signal::derive(move || {
+        r_vehicle().unwrap_or_else(|| trips.with(|tr| tr.vehicles.last_used()))
+    });
+    let cost_centres = Signal::derive(move || trips.with(|tr| tr.cost_centres.all().to_vec()));
+    let (r_cost_centre, w_cost_centre) =
+        create_signal(editing.as_ref().map(|t| t.cost_centre.clone()));
+    // Follows the destination unless the user picks a code.
+    let cost_centre = Signal::derive(move || {
+        r_cost_centre().unwrap_or_else(|| {
+            with!(|trips, r_to| {
+                let to = r_to.as_ref()?;
+                trips.cost_centres.suggest(trips.trips.iter(), to)
+            })
+        })
+    });
+    let readings = editing.as_ref().and_then(|t| t.odometer);
+    let (r_start, w_start) = create_signal(readings.map(|o| o.start));
+    let (r_end, w_end) = create_signal(readings.map(|o| o.end));
+    // Continues from the last reading on the same vehicle unless the user says otherwise.
+    let start = Signal::derive(move || {
+        r_start().or_else(|| {
+            trips.with(|tr| {
+                odometer::last_reading(tr.trips.iter().filter(|t| t.vehicle == vehicle()))
+            })
+        })
+    });
+    let odometer = Signal::derive(move || Odometer::new(start()?, r_end()?));
     let today = Local::now().date_naive().to_string();
+    let date = editing
+        .as_ref()
+        .map_or_else(|| today.clone(), |t| t.date.to_string());
+    let (r_reason, w_reason) = create_signal(editing.as_ref().map(|t| t.reason.clone()));
+    let usual_reasons = Signal::derive(move || {
+        with!(|trips, r_from, r_to| match (r_from, r_to) {
+            (Some(from), Some(to)) => trips.reasons_between(from, to),
+            _ => Vec::new(),
+        })
+    });
+    // Follows the route until the user writes a reason of their own.
+    let reason = Signal::derive(move || {
+        r_reason()
+            .or_else(|| usual_reasons.with(|r| r.first().cloned()))
+            .unwrap_or_else(|| "Möte".to_owned())
+    });
+    let other_reasons = Signal::derive(move || {
+        usual_reasons.with(|r| {
+            r.iter()
+                .filter(|r| **r != reason())
+                .take(3)
+                .cloned()
+                .collect_vec()
+        })
+    });
+    let category = editing.as_ref().map(|t| t.category).unwrap_or_default();
+    let categories = Category::ALL
+        .iter()
+        .map(|c| {
+            view! {
+                <option value=format!("{c:?}") selected=*c == category>
+                    {c.for_humans()}
+                </option>
+            }
+        })
+        .collect_view();
+    let edited = editing.as_ref().map(|t| t.uuid);
     let zero_out = move || {
         w_distance(0.);
         w_time(0)
     };
+    let route = Signal::derive(move || {
+        with!(|r_from, r_to, r_stops, r_custom| {
+            let (Some(f), Some(t)) = (r_from, r_to) else {
+                return None;
+            };
+            // Other spellings and aliases are looked up under the name they stand for.
+            let matcher = PlaceMatcher::new(r_custom);
+            let route = std::iter::once(f)
+                .chain(r_stops.iter().filter(|s| !s.is_empty()))
+                .chain(std::iter::once(t))
+                .map(|p| matcher.resolve(p))
+                .collect_vec();
+            route.iter().all(|p| !p.is_empty()).then_some(route)
+        })
+    });
+    // Replaces what was typed with the name it certainly means once the user leaves the field.
+    let snap = move |typed: &str| r_custom.with_untracked(|c| PlaceMatcher::new(c).resolve(typed));
+    // Which sources the distance came from, cleared when the user types their own.
+    let (r_source, w_source) = create_signal(None::<String>);
+    // Set while the distance is an accepted estimate, which must not pass for the user's own.
+    let (r_guessed, w_guessed) = create_signal(false);
     let autopilot = move || {
-        with!(move |r_from, r_to, r_custom| {
-            if let (Some(f), Some(t)) = (r_from, r_to) {
-                if !f.is_empty() && !t.is_empty() {
-                    if let Some(travel_data) = r_custom
-                        .get(f, t)
-                        .or_else(|| r_custom.get(t, f))
-                        .or_else(|| travel(f, t))
-                    {
-                        w_distance(travel_data.km());
-                        w_time(travel_data.minutes());
-                    } else {
-                        zero_out()
-                    };
-                } else {
-                    zero_out();
-                }
-            } else {
-                zero_out();
+        w_guessed(false);
+        let found = with!(|route, r_custom, r_settings| {
+            TravelResolver::new(r_custom, r_settings.compose_routes).route(route.as_ref()?)
+        });
+        if let Some(resolved) = found {
+            w_distance(resolved.travel.km());
+            w_time(resolved.travel.minutes());
+            w_source(Some(resolved.describe_sources()));
+        } else {
+            zero_out();
+            w_source(None);
+        }
+    };
+    // Only offered when neither the matrix nor the user knows every leg.
+    let estimated = Signal::derive(move || {
+        with!(|route, r_custom, r_settings| {
+            let route = route.as_ref()?;
+            let resolver = TravelResolver::new(r_custom, r_settings.compose_routes);
+            if resolver.route(route).is_some() {
+                return None;
             }
+            total(route.windows(2).map(|w| {
+                resolver.resolve(&w[0], &w[1]).map(|(t, _)| t).or_else(|| {
+                    Some(estimate(
+                        r_custom.locate(&w[0])?,
+                        r_custom.locate(&w[1])?,
+                        r_settings.winding_factor,
+                        r_settings.average_speed,
+                    ))
+                })
+            }))
         })
+    });
+    let accept_estimate = move |_| {
+        if let Some(e) = estimated.get_untracked() {
+            if !odometer_mode.get_untracked() {
+                w_distance(e.km());
+                w_source(Some("uppskattning".to_owned()));
+                w_guessed(true);
+            }
+            w_time(e.minutes().max(1));
+        }
     };
+    // A rundresa may well end where it started.
     let cleaner = move || {
-        if r_from.get_untracked() == r_to.get_untracked() {
+        if r_from.get_untracked() == r_to.get_untracked() && r_stops.with_untracked(Vec::is_empty) {
             w_to(None)
         }
     };
@@ -196,23 +367,86 @@ pub fn AddTravel(
         let tim = gloo::timers::callback::Timeout::new(40, move || w_returning(Some(false)));
         tim.forget();
     };
-    create_effect(move |_| {
+    create_effect(move |ran_before: Option<()>| {
         r_from.track();
         r_to.track();
-        autopilot();
+        r_stops.track();
+        // An edited trip keeps its own distance and time until the route changes.
+        if ran_before.is_some() || edited.is_none() {
+            autopilot();
+        }
         cleaner();
     });
+    create_effect(move |_| {
+        if let (true, Some(o)) = (odometer_mode(), odometer()) {
+            let ways = if r_returning().unwrap_or_default() {
+                2.
+            } else {
+                1.
+            };
+            w_distance(o.distance() as f32 / ways);
+        }
+    });
     let new = move |ev: SubmitEvent| {
         ev.prevent_default();
-        let t = Trip::from_event(&ev);
-        if let Ok(t) = t {
-            if travel(&t.from, &t.to).is_none() {
-                w_custom.update(|ct| ct.add(&t));
+        let t = Trip::from_event(&ev).map(|mut t| {
+            if odometer_mode.get_untracked() {
+                t.odometer = odometer.get_untracked();
+            } else {
+                // The form has no readings then, so an edited trip keeps the ones it had, unless
+                // they would override a distance the user has changed.
+                let ways = if t.returning { 2. } else { 1. };
+                t.odometer =
+                    readings.filter(|o| (o.distance() as f32 / ways - t.distance).abs() < 0.05);
+            }
+            t.vehicle = vehicle.get_untracked();
+            t.cost_centre = cost_centre.get_untracked();
+            t.stops =
+                r_stops.with_untracked(|s| s.iter().filter(|s| !s.is_empty()).cloned().collect());
+            // Saved under the known names, so the same place is not remembered twice.
+            r_custom.with_untracked(|c| {
+                let matcher = PlaceMatcher::new(c);
+                t.from = matcher.resolve(&t.from);
+                t.to = matcher.resolve(&t.to);
+                t.stops.iter_mut().for_each(|s| *s = matcher.resolve(s));
+            });
+            t
+        });
+        if let Ok(mut t) = t {
+            if odometer_mode.get_untracked() && t.odometer.is_none() {
+                return;
+            }
+            // Anything the matrix already knows, either way round, is not worth a correction, and
+            // a guess is not one at all.
+            if t.stops.is_empty() && !r_guessed.get_untracked() {
+                w_custom.update(|ct| {
+                    if TravelResolver::new(ct, false)
+                        .matrix(&t.from, &t.to)
+                        .is_none()
+                    {
+                        ct.add(&t);
+                    }
+                });
             };
+            if let Some(uuid) = edited {
+                t.uuid = uuid;
+                t.edited = Some(Local::now().naive_local());
+                write_to.update(|tr: &mut Trips| tr.update(t));
+                if let Some(on_saved) = on_saved {
+                    on_saved(());
+                }
+                return;
+            }
             write_to.update(|tr: &mut Trips| {
+                tr.vehicles.use_vehicle(t.vehicle);
                 tr.add(t);
             });
             zero_out();
+            w_stops(Vec::new());
+            w_cost_centre(None);
+            w_reason(None);
+            w_start(None);
+            w_end(None);
             if returning().is_some_and(|r| r).not() {
                 w_from(r_to());
             }
@@ -228,7 +462,7 @@ pub fn AddTravel(
                     <input
                         name="date"
                         type="date"
-                        value=today.clone()
+                        value=date
                         max=today
                         class="input input-bordered w-full max-w-xs"
                         required
@@ -245,9 +479,57 @@ pub fn AddTravel(
                         on:input=move |ev| {
                             w_from(Some(event_target_value(&ev)));
                         }
+                        on:change=move |ev| w_from(Some(snap(&event_target_value(&ev))))
                     />
 
                 </InputWrap>
+                <DidYouMean typed=r_from.into() on_accept=Callback::new(move |n| w_from(Some(n)))/>
+                <For each=move || 0..r_stops.with(Vec::len) key=|i| *i let:i>
+                    <InputWrap label="Via" extra=format!("stopp {}", i + 1)>
+                        <div class="flex gap-2 w-full max-w-xs">
+                            <input
+                                prop:value=move || {
+                                    r_stops.with(|s| s.get(i).cloned().unwrap_or_default())
+                                }
+
+                                list="destination-choices"
+                                class="input input-bordered w-full"
+                                required
+                                on:input=move |ev| {
+                                    w_stops.update(|s| s[i] = event_target_value(&ev));
+                                }
+                                on:change=move |ev| {
+                                    w_stops.update(|s| s[i] = snap(&event_target_value(&ev)));
+                                }
+                            />
+
+                            <button
+                                type="button"
+                                class="btn btn-ghost btn-circle text-secondary"
+                                on:click=move |_| {
+                                    w_stops
+                                        .update(|s| {
+                                            s.remove(i);
+                                        })
+                                }
+                            >
+
+                                <Icon class="size-6" icon=icondata::TiDeleteOutline/>
+                            </button>
+                        </div>
+                    </InputWrap>
+                    <DidYouMean
+                        typed=Signal::derive(move || r_stops.with(|s| s.get(i).cloned()))
+                        on_accept=Callback::new(move |n| w_stops.update(|s| s[i] = n))
+                    />
+                </For>
+                <button
+                    type="button"
+                    class="btn btn-sm btn-ghost self-start"
+                    on:click=move |_| w_stops.update(|s| s.push(String::new()))
+                >
+                    "+ Lägg till stopp"
+                </button>
                 <InputWrap label="Resmål">
                     <input
                         name="to"
@@ -259,14 +541,117 @@ pub fn AddTravel(
                         on:input=move |ev| {
                             w_to(Some(event_target_value(&ev)));
                         }
+                        on:change=move |ev| w_to(Some(snap(&event_target_value(&ev))))
                     />
 
                 </InputWrap>
+                <DidYouMean typed=r_to.into() on_accept=Callback::new(move |n| w_to(Some(n)))/>
+                <Show when=move || vehicles.with(|v| !v.is_empty())>
+                    <InputWrap label="Fordon">
+                        <select
+                            class="select select-bordered w-full max-w-xs"
+                            on:change=move |ev| {
+                                w_vehicle(Some(Uuid::parse_str(&event_target_value(&ev)).ok()));
+                                w_start(None);
+                            }
+                        >
+
+                            <option value="" selected=move || vehicle().is_none()>
+                                Inget fordon
+                            </option>
+                            <For each=vehicles key=|v| v.id let:v>
+                                <option
+                                    value=v.id.to_string()
+                                    selected=move || vehicle() == Some(v.id)
+                                >
+                                    {v.for_humans()}
+                                </option>
+                            </For>
+                        </select>
+                    </InputWrap>
+                </Show>
+                <Show when=move || cost_centres.with(|c| !c.is_empty())>
+                    <InputWrap label="Kostnadsställe">
+                        <select
+                            class="select select-bordered w-full max-w-xs"
+                            on:change=move |ev| {
+                                let code = event_target_value(&ev);
+                                w_cost_centre(Some((!code.is_empty()).then_some(code)));
+                            }
+                        >
+
+                            <option value="" selected=move || cost_centre().is_none()>
+                                Inget kostnadsställe
+                            </option>
+                            <For each=cost_centres key=|c| c.code.clone() let:c>
+                                <option
+                                    value=c.code.clone()
+                                    selected={
+                                        let code = c.code.clone();
+                                        move || cost_centre().as_ref() == Some(&code)
+                                    }
+                                >
+
+                                    {c.for_humans()}
+                                </option>
+                            </For>
+                        </select>
+                    </InputWrap>
+                </Show>
+                <Show when=odometer_mode>
+                    <div class="flex gap-2">
+                        <InputWrap label="Mätare start" explanation="kilometer">
+                            <input
+                                type="number"
+                                min=0
+                                step=1
+                                inputmode="numeric"
+                                class="input input-bordered w-full max-w-xs"
+                                required
+                                prop:value=move || start().map(|s| s.to_string()).unwrap_or_default()
+                                on:input=move |ev| {
+                                    w_start(event_target_value(&ev).parse().ok());
+                                }
+                            />
+
+                        </InputWrap>
+                        <InputWrap
+                            label="Mätare slut"
+                            explanation="kilometer"
+                            error=Signal::derive(move || {
+                                match (r_end(), odometer()) {
+                                    (Some(_), None) => "Mindre än start".to_string(),
+                                    _ => String::new(),
+                                }
+                            })
+                        >
+
+                            <input
+                                type="number"
+                                min=0
+                                step=1
+                                inputmode="numeric"
+                                class="input input-bordered w-full max-w-xs"
+                                required
+                                prop:value=move || r_end().map(|s| s.to_string()).unwrap_or_default()
+                                on:input=move |ev| {
+                                    w_end(event_target_value(&ev).parse().ok());
+                                }
+                            />
+
+                        </InputWrap>
+                    </div>
+                </Show>
                 <div class="flex gap-2">
-                    <InputWrap label="Avstånd" explanation="kilometer">
+                    <InputWrap
+                        label="Avstånd"
+                        explanation="kilometer"
+                        extra=Signal::derive(move || r_source().unwrap_or_default())
+                    >
                         <input
                             name="distance"
                             type="number"
+                            readonly=odometer_mode
                             required
                             min=0.1
                             max=1000
@@ -278,7 +663,9 @@ pub fn AddTravel(
                                 let s: String = event_target_value(&ev);
                                 let d = s.parse::<f32>();
                                 if let Ok(d) = d {
-                                    w_distance(d)
+                                    w_distance(d);
+                                    w_source(None);
+                                    w_guessed(false);
                                 }
                             }
                         />
@@ -306,14 +693,56 @@ pub fn AddTravel(
 
                     </InputWrap>
                 </div>
+                {move || {
+                    estimated()
+                        .map(|e| {
+                            view! {
+                                <div class="alert text-sm flex justify-between">
+                                    <span>"Uppskattning: " {e.describe()}</span>
+                                    <button
+                                        type="button"
+                                        class="btn btn-xs btn-outline"
+                                        on:click=accept_estimate
+                                    >
+                                        Använd
+                                    </button>
+                                </div>
+                            }
+                        })
+                }}
+
                 <InputWrap label="Anledning">
                     <input
                         name="reason"
-                        value="Möte"
+                        prop:value=reason
+                        list="reason-choices"
                         class="input input-bordered w-full max-w-xs"
                         required
+                        on:input=move |ev| w_reason(Some(event_target_value(&ev)))
                     />
                 </InputWrap>
+                <Show when=move || other_reasons.with(|r| !r.is_empty())>
+                    <div class="flex flex-wrap gap-2">
+                        <For each=other_reasons key=|r| r.clone() let:other>
+                            <button
+                                type="button"
+                                class="btn btn-xs btn-ghost"
+                                on:click={
+                                    let other = other.clone();
+                                    move |_| w_reason(Some(other.clone()))
+                                }
+                            >
+
+                                {other}
+                            </button>
+                        </For>
+                    </div>
+                </Show>
+                <InputWrap label="Kategori">
+                    <select name="category" class="select select-bordered w-full max-w-xs">
+                        {categories}
+                    </select>
+                </InputWrap>
 
                 <div class="form-control">
                     <label
@@ -336,24 +765,81 @@ pub fn AddTravel(
                 </div>
 
                 <button type="submit" class="btn btn-secondary btn-outline">
-                    Lägg in
+                    {if edited.is_some() { "Spara ändringar" } else { "Lägg in" }}
                 </button>
             </form>
         </div>
     }
 }
 
+/// Offers a known name close to what was typed, which the user may take or leave be.
 #[component]
-fn DestinationDataList() -> impl IntoView {
-    let (r_custom, _, _) = use_local_storage::<CustomTrips, JsonCodec>("my-custom-trips");
+fn DidYouMean(typed: Signal<Option<String>>, on_accept: Callback<String>) -> impl IntoView {
+    let (r_custom, _) = use_custom_trips();
+    let suggestion = Signal::derive(move || {
+        with!(|typed, r_custom| PlaceMatcher::new(r_custom).suggest(typed.as_deref()?))
+    });
+    move || {
+        suggestion().map(|name| {
+            let shown = name.clone();
+            view! {
+                <button
+                    type="button"
+                    class="btn btn-xs btn-ghost self-start"
+                    on:click=move |_| on_accept(name.clone())
+                >
+                    "Menade du " {shown} "?"
+                </button>
+            }
+        })
+    }
+}
+
+/// Every reason used before, the most used first.
+#[component]
+pub fn ReasonDataList() -> impl IntoView {
+    let (r_trips, _) = use_trips();
+    let options = Signal::derive(move || r_trips.with(Trips::reasons));
+    view! {
+        <datalist id="reason-choices">
+            <For each=options key=|r| r.clone() let:reason>
+                <option value=reason></option>
+            </For>
+        </datalist>
+    }
+}
+
+#[component]
+pub fn DestinationDataList() -> impl IntoView {
+    let (r_custom, _) = use_custom_trips();
     let options = Signal::derive(move || {
-        r_custom.with(|c| c.trips.keys().cloned().chain(destinations()).collect_vec())
+        r_custom.with(|c| {
+            c.places()
+                .cloned()
+                .chain(destinations())
+                .unique()
+                .collect_vec()
+        })
+    });
+    // Offered under their nicknames too, but always filled in with the real name.
+    let aliases = Signal::derive(move || {
+        r_custom.with(|c| {
+            c.aliases()
+                .iter()
+                .chain(ALIASES.iter())
+                .map(|(alias, place)| (alias.clone(), place.clone()))
+                .unique_by(|(alias, _)| alias.clone())
+                .collect_vec()
+        })
     });
     view! {
         <datalist id="destination-choices">
             <For each=options key=move |k| k.clone() let:name>
                 <option value=name></option>
             </For>
+            <For each=aliases key=|(alias, _)| alias.clone() let:alias>
+                <option value=alias.1 label=alias.0></option>
+            </For>
 
         </datalist>
     }
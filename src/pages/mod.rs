@@ -1,3 +1,7 @@
+pub mod checkpoint;
+pub mod dashboard;
+pub mod data;
 pub mod home;
 pub mod not_found;
-pub mod checkpoint;
+pub mod places;
+pub mod settings;
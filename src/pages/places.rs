@@ -0,0 +1,409 @@
+use chrono::Local;
+use itertools::Itertools;
+use leptos::*;
+use leptos_icons::Icon;
+use leptos_router::FromFormData;
+use serde::Deserialize;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlFormElement, SubmitEvent};
+
+use crate::{
+    components::InputWrap,
+    destinations::{destinations, fold, in_matrix, use_custom_trips, Coordinates, PlaceMatcher},
+    pages::home::DestinationDataList,
+    storage::use_trips,
+};
+
+#[component]
+pub fn Places() -> impl IntoView {
+    let selected = create_rw_signal(None::<String>);
+    view! {
+        <div class="min-h-svh py-12">
+            <DestinationDataList/>
+            <div class="w-11/12 flex flex-col lg:flex-row justify-center items-center lg:items-start gap-12">
+                <PlaceList selected/>
+                <Show when=move || selected.with(Option::is_some)>
+                    <PlaceEditor selected/>
+                </Show>
+                <Aliases/>
+            </div>
+        </div>
+    }
+}
+
+/// One known place and where the app knows it from.
+#[derive(Debug, Clone, PartialEq)]
+struct Known {
+    name: String,
+    matrix: bool,
+    custom: bool,
+    trips: usize,
+}
+
+impl Known {
+    fn sources(&self) -> Vec<&'static str> {
+        let mut sources = Vec::new();
+        if self.matrix {
+            sources.push("Avståndstabellen");
+        }
+        if self.custom {
+            sources.push("Egen");
+        }
+        if !self.matrix && !self.custom {
+            sources.push("Bara i resor");
+        }
+        sources
+    }
+}
+
+#[component]
+fn PlaceList(selected: RwSignal<Option<String>>) -> impl IntoView {
+    let (r_trips, _) = use_trips();
+    let (r_custom, _) = use_custom_trips();
+    let (r_filter, w_filter) = create_signal(String::new());
+    let known = Signal::derive(move || {
+        with!(|r_trips, r_custom, r_filter| {
+            let counts = r_trips.place_counts();
+            let filter = fold(r_filter);
+            destinations()
+                .into_iter()
+                .chain(r_custom.places().cloned())
+                .chain(r_custom.coordinates().keys().cloned())
+                .chain(counts.keys().map(|p| p.to_string()))
+                .unique()
+                .filter(|name| fold(name).contains(&filter))
+                .sorted_by_cached_key(|name| fold(name))
+                .map(|name| Known {
+                    matrix: in_matrix(&name),
+                    custom: r_custom.knows(&name),
+                    trips: counts.get(name.as_str()).copied().unwrap_or_default(),
+                    name,
+                })
+                .collect_vec()
+        })
+    });
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Platser</h2>
+            <input
+                type="search"
+                placeholder="Sök"
+                class="input input-bordered input-sm w-full"
+                on:input=move |ev| w_filter(event_target_value(&ev))
+            />
+            <ul role="list" class="divide-y divide-gray-100">
+                <For each=known key=|k| (k.name.clone(), k.custom, k.trips) let:place>
+                    <li
+                        class="flex justify-between items-center gap-2 py-2 text-sm cursor-pointer"
+                        class:font-bold={
+                            let name = place.name.clone();
+                            move || selected.with(|s| s.as_ref() == Some(&name))
+                        }
+
+                        on:click={
+                            let name = place.name.clone();
+                            move |_| selected.set(Some(name.clone()))
+                        }
+                    >
+
+                        <span class="flex-1">{place.name.clone()}</span>
+                        {place
+                            .sources()
+                            .into_iter()
+                            .map(|s| view! { <span class="badge badge-ghost badge-sm">{s}</span> })
+                            .collect_view()}
+                        <span class="w-16 text-right">{format!("{} resor", place.trips)}</span>
+                    </li>
+                </For>
+            </ul>
+        </div>
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Rename {
+    name: String,
+    /// An unchecked checkbox is left out of the form data.
+    #[serde(default)]
+    rewrite: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Position {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Merge {
+    into: String,
+}
+
+/// Everything that can be changed about the selected place.
+#[component]
+fn PlaceEditor(selected: RwSignal<Option<String>>) -> impl IntoView {
+    let (r_trips, w_trips) = use_trips();
+    let (r_custom, w_custom) = use_custom_trips();
+    let place = move || selected().unwrap_or_default();
+    let matrix = move || in_matrix(&place());
+    let (r_message, w_message) = create_signal(None::<String>);
+    create_effect(move |_| {
+        selected.track();
+        w_message(None);
+    });
+    let address = move || r_custom.with(|c| c.address(&place()).cloned().unwrap_or_default());
+    let coordinates = move || r_custom.with(|c| c.locate(&place()));
+    let own_coordinates = move || r_custom.with(|c| c.coordinates().contains_key(&place()));
+    let trip_count = move || {
+        r_trips.with(|tr| {
+            tr.place_counts()
+                .get(place().as_str())
+                .copied()
+                .unwrap_or_default()
+        })
+    };
+    let move_place = move |old: &str, new: &str, rewrite: bool| {
+        w_custom.update(|c| c.rename_place(old, new));
+        let changed = if rewrite {
+            let now = Local::now().naive_local();
+            let mut changed = 0;
+            w_trips.update(|tr| changed = tr.rename_place(old, new, now));
+            changed
+        } else {
+            0
+        };
+        selected.set(Some(new.to_owned()));
+        changed
+    };
+    let rename = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let Ok(Rename { name, rewrite }) = Rename::from_event(&ev) else {
+            return;
+        };
+        let (old, name) = (place(), name.trim().to_owned());
+        if name.is_empty() || name == old {
+            return;
+        }
+        let changed = move_place(&old, &name, rewrite);
+        w_message(Some(format!(
+            "Bytte namn till {name}, {changed} resor ändrade"
+        )));
+    };
+    let merge = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let Ok(Merge { into }) = Merge::from_event(&ev) else {
+            return;
+        };
+        let old = place();
+        let into = r_custom.with_untracked(|c| PlaceMatcher::new(c).resolve(&into));
+        if into.is_empty() || into == old {
+            return;
+        }
+        // Duplicates are the same place, so the trips follow along and the old name keeps working.
+        let changed = move_place(&old, &into, true);
+        w_custom.update(|c| c.set_alias(old.clone(), into.clone()));
+        w_message(Some(format!("{old} är nu {into}, {changed} resor ändrade")));
+    };
+    let position = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        if let Ok(Position { lat, lon }) = Position::from_event(&ev) {
+            w_custom.update(|c| c.set_coordinates(place(), Coordinates { lat, lon }));
+            if let Some(form) = ev.target() {
+                form.unchecked_into::<HtmlFormElement>().reset();
+            }
+        }
+    };
+    let forget = move |_| {
+        let old = place();
+        w_custom.update(|c| c.remove_place(&old));
+        w_message(Some(format!("Egna uppgifter om {old} borttagna")));
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">{place}</h2>
+            <p class="text-sm">
+                {move || format!("Används i {} resor.", trip_count())}
+                {move || {
+                    coordinates()
+                        .map(|c| format!(" Ligger på {:.5}, {:.5}.", c.lat, c.lon))
+                        .unwrap_or_else(|| " Saknar koordinater.".to_owned())
+                }}
+
+            </p>
+            {move || r_message().map(|m| view! { <p class="text-sm text-success">{m}</p> })}
+            <InputWrap label="Adress">
+                <input
+                    class="input input-bordered w-full max-w-xs"
+                    prop:value=address
+                    on:change=move |ev| {
+                        w_custom.update(|c| c.set_address(place(), event_target_value(&ev)))
+                    }
+                />
+
+            </InputWrap>
+            <form on:submit=position class="flex flex-col gap-3">
+                <div class="flex gap-2">
+                    <InputWrap label="Latitud">
+                        <input
+                            name="lat"
+                            type="number"
+                            min=-90
+                            max=90
+                            step="any"
+                            class="input input-bordered w-full max-w-xs"
+                            required
+                        />
+                    </InputWrap>
+                    <InputWrap label="Longitud">
+                        <input
+                            name="lon"
+                            type="number"
+                            min=-180
+                            max=180
+                            step="any"
+                            class="input input-bordered w-full max-w-xs"
+                            required
+                        />
+                    </InputWrap>
+                </div>
+                <div class="flex gap-2">
+                    <button type="submit" class="btn btn-sm btn-secondary btn-outline">
+                        Spara koordinater
+                    </button>
+                    <Show when=own_coordinates>
+                        <button
+                            type="button"
+                            class="btn btn-sm btn-ghost"
+                            on:click=move |_| w_custom.update(|c| c.remove_coordinates(&place()))
+                        >
+                            Ta bort koordinater
+                        </button>
+                    </Show>
+                </div>
+            </form>
+            <Show
+                when=move || !matrix()
+                fallback=|| {
+                    view! {
+                        <p class="text-sm">
+                            Platser i avståndstabellen kan inte byta namn, men de kan få smeknamn.
+                        </p>
+                    }
+                }
+            >
+
+                <form on:submit=rename class="flex flex-col gap-3">
+                    <InputWrap label="Nytt namn">
+                        <input
+                            name="name"
+                            class="input input-bordered w-full max-w-xs"
+                            prop:value=place
+                            required
+                        />
+                    </InputWrap>
+                    <label class="label cursor-pointer justify-start gap-3">
+                        <input type="checkbox" name="rewrite" value="true" class="checkbox"/>
+                        <span class="label-text">Ändra även i tidigare resor</span>
+                    </label>
+                    <button type="submit" class="btn btn-sm btn-secondary btn-outline">
+                        Byt namn
+                    </button>
+                </form>
+                <form on:submit=merge class="flex flex-col gap-3">
+                    <InputWrap label="Slå ihop med" explanation="resorna flyttas dit">
+                        <input
+                            name="into"
+                            list="destination-choices"
+                            class="input input-bordered w-full max-w-xs"
+                            required
+                        />
+                    </InputWrap>
+                    <button type="submit" class="btn btn-sm btn-secondary btn-outline">
+                        Slå ihop
+                    </button>
+                </form>
+            </Show>
+            <Show when=move || r_custom.with(|c| c.knows(&place()))>
+                <button on:click=forget class="btn btn-sm btn-outline btn-error">
+                    Glöm egna uppgifter
+                </button>
+            </Show>
+        </div>
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Alias {
+    alias: String,
+    place: String,
+}
+
+/// The user's own names for places, filled in as the real name when logging a trip.
+#[component]
+pub fn Aliases() -> impl IntoView {
+    let (r_custom, w_custom) = use_custom_trips();
+    let aliases = Signal::derive(move || {
+        r_custom.with(|c| {
+            let mut aliases = c
+                .aliases()
+                .iter()
+                .map(|(a, p)| (a.clone(), p.clone()))
+                .collect::<Vec<_>>();
+            aliases.sort();
+            aliases
+        })
+    });
+    let new = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        if let Ok(Alias { alias, place }) = Alias::from_event(&ev) {
+            w_custom.update(|c| {
+                let place = PlaceMatcher::new(c).resolve(&place);
+                c.set_alias(alias.trim().to_owned(), place);
+            });
+            if let Some(form) = ev.target() {
+                form.unchecked_into::<HtmlFormElement>().reset();
+            }
+        }
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Smeknamn</h2>
+            <p class="text-sm">
+                Skriv ett smeknamn när du loggar en resa så fylls platsens riktiga namn i.
+            </p>
+            <ul role="list" class="divide-y divide-gray-100">
+                <For each=aliases key=|(a, _)| a.clone() let:alias>
+                    <li class="flex justify-between items-center py-2 text-sm">
+                        <span>{alias.0.clone()}</span>
+                        <span>{alias.1.clone()}</span>
+                        <button
+                            class="btn btn-ghost btn-circle btn-sm text-secondary"
+                            on:click=move |_| w_custom.update(|c| c.remove_alias(&alias.0))
+                        >
+                            <Icon class="size-5" icon=icondata::TiDeleteOutline/>
+                        </button>
+                    </li>
+                </For>
+            </ul>
+            <form on:submit=new class="flex flex-col gap-3">
+                <InputWrap label="Smeknamn">
+                    <input name="alias" class="input input-bordered w-full max-w-xs" required/>
+                </InputWrap>
+                <InputWrap label="Plats">
+                    <input
+                        name="place"
+                        list="destination-choices"
+                        class="input input-bordered w-full max-w-xs"
+                        required
+                    />
+                </InputWrap>
+                <button type="submit" class="btn btn-secondary btn-outline">
+                    Spara smeknamn
+                </button>
+            </form>
+        </div>
+    }
+}
@@ -0,0 +1,597 @@
+use chrono::Local;
+use leptos::*;
+use leptos_icons::Icon;
+use leptos_router::{FromFormData, A};
+use uuid::Uuid;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlFormElement, SubmitEvent};
+
+use crate::{
+    components::InputWrap,
+    cost_centres::CostCentre,
+    favorites::Route,
+    reimbursement::{kronor, Rate, TAX_FREE},
+    report::{self, Column, Layout, Template},
+    settings::{use_settings, Settings},
+    storage::use_trips,
+    vehicles::{Fuel, Ownership, Vehicle},
+};
+
+#[component]
+pub fn SettingsPage() -> impl IntoView {
+    view! {
+        <div class="min-h-svh py-12">
+            <div class="w-11/12 flex flex-col lg:flex-row justify-center items-center lg:items-start gap-12">
+                <Personal/>
+                <VehicleRegistry/>
+                <CostCentreRegistry/>
+                <EmployerRates/>
+                <Estimates/>
+                <FavoriteRanking/>
+                <ReportTemplates/>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn Personal() -> impl IntoView {
+    let (r_settings, w_settings) = use_settings();
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-3">
+            <h2 class="text-xl font-medium">Körjournal</h2>
+            <InputWrap label="Namn">
+                <input
+                    class="input input-bordered w-full max-w-xs"
+                    prop:value=move || r_settings.with(|s| s.employee.clone())
+                    on:change=move |ev| w_settings.update(|s| s.employee = event_target_value(&ev))
+                />
+            </InputWrap>
+            <div class="form-control">
+                <label class="label cursor-pointer justify-start align-center gap-3">
+                    <input
+                        type="checkbox"
+                        class="toggle toggle-primary"
+                        prop:checked=move || r_settings.with(|s| s.odometer)
+                        on:change=move |ev| {
+                            w_settings.update(|s| s.odometer = event_target_checked(&ev))
+                        }
+                    />
+                    <span class="label-text">Mätarställning för varje resa</span>
+                </label>
+            </div>
+            <InputWrap label="Papperskorg" explanation="dagar innan raderade resor försvinner">
+                <input
+                    type="number"
+                    min=1
+                    step=1
+                    inputmode="numeric"
+                    class="input input-bordered w-full max-w-xs"
+                    prop:value=move || r_settings.with(|s| s.trash_days.to_string())
+                    on:change=move |ev| {
+                        if let Ok(days @ 1..) = event_target_value(&ev).parse() {
+                            w_settings.update(|s| s.trash_days = days);
+                        }
+                    }
+                />
+
+            </InputWrap>
+        </div>
+    }
+}
+
+#[component]
+pub fn VehicleRegistry() -> impl IntoView {
+    let (r_trips, w_trips) = use_trips();
+    let vehicles = Signal::derive(move || r_trips.with(|tr| tr.vehicles.all().to_vec()));
+    let in_use = move |id: Uuid| r_trips.with(|tr| tr.trips.iter().any(|t| t.vehicle == Some(id)));
+    let new = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        if let Ok(vehicle) = Vehicle::from_event(&ev) {
+            w_trips.update(|tr| tr.vehicles.add(vehicle));
+            if let Some(form) = ev.target() {
+                form.unchecked_into::<HtmlFormElement>().reset();
+            }
+        }
+    };
+    let ownerships = Ownership::ALL
+        .iter()
+        .map(|o| view! { <option value=format!("{o:?}")>{o.for_humans()}</option> })
+        .collect_view();
+    let fuels = Fuel::ALL
+        .iter()
+        .map(|f| view! { <option value=format!("{f:?}")>{f.for_humans()}</option> })
+        .collect_view();
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Fordon</h2>
+            <ul role="list" class="divide-y divide-gray-100">
+                <For each=vehicles key=|v| v.id let:vehicle>
+                    <li class="flex justify-between items-center py-2 text-sm">
+                        <div class="flex flex-col">
+                            <span class="font-medium">{vehicle.for_humans()}</span>
+                            <span class="text-xs text-gray-500">
+                                {vehicle.ownership.for_humans()} ", "
+                                {vehicle.fuel.for_humans().to_lowercase()}
+                            </span>
+                        </div>
+                        <button
+                            class="btn btn-ghost btn-circle btn-sm text-secondary"
+                            title="Fordon med resor kan inte tas bort"
+                            disabled=move || in_use(vehicle.id)
+                            on:click=move |_| w_trips.update(|tr| tr.vehicles.remove(&vehicle.id))
+                        >
+                            <Icon class="size-5" icon=icondata::TiDeleteOutline/>
+                        </button>
+                    </li>
+                </For>
+            </ul>
+            <form on:submit=new class="flex flex-col gap-3">
+                <div class="flex gap-2">
+                    <InputWrap label="Registreringsnummer">
+                        <input
+                            name="registration"
+                            class="input input-bordered w-full max-w-xs uppercase"
+                            required
+                        />
+                    </InputWrap>
+                    <InputWrap label="Namn" explanation="valfritt">
+                        <input name="name" class="input input-bordered w-full max-w-xs"/>
+                    </InputWrap>
+                </div>
+                <div class="flex gap-2">
+                    <InputWrap label="Typ">
+                        <select name="ownership" class="select select-bordered w-full max-w-xs">
+                            {ownerships}
+                        </select>
+                    </InputWrap>
+                    <InputWrap label="Drivmedel">
+                        <select name="fuel" class="select select-bordered w-full max-w-xs">
+                            {fuels}
+                        </select>
+                    </InputWrap>
+                </div>
+                <button type="submit" class="btn btn-secondary btn-outline">
+                    Lägg till fordon
+                </button>
+            </form>
+        </div>
+    }
+}
+
+#[component]
+pub fn CostCentreRegistry() -> impl IntoView {
+    let (r_trips, w_trips) = use_trips();
+    let centres = Signal::derive(move || r_trips.with(|tr| tr.cost_centres.all().to_vec()));
+    let in_use = move |code: &str| {
+        r_trips.with(|tr| {
+            tr.trips
+                .iter()
+                .any(|t| t.cost_centre.as_deref() == Some(code))
+        })
+    };
+    let new = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        if let Ok(centre) = CostCentre::from_event(&ev) {
+            w_trips.update(|tr| tr.cost_centres.add(centre));
+            if let Some(form) = ev.target() {
+                form.unchecked_into::<HtmlFormElement>().reset();
+            }
+        }
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Kostnadsställen</h2>
+            <ul role="list" class="divide-y divide-gray-100">
+                <For each=centres key=|c| (c.code.clone(), c.name.clone()) let:centre>
+                    <li class="flex justify-between items-center py-2 text-sm">
+                        <span class="font-medium">{centre.for_humans()}</span>
+                        <button
+                            class="btn btn-ghost btn-circle btn-sm text-secondary"
+                            title="Kostnadsställen med resor kan inte tas bort"
+                            disabled={
+                                let code = centre.code.clone();
+                                move || in_use(&code)
+                            }
+
+                            on:click=move |_| {
+                                w_trips.update(|tr| tr.cost_centres.remove(&centre.code))
+                            }
+                        >
+                            <Icon class="size-5" icon=icondata::TiDeleteOutline/>
+                        </button>
+                    </li>
+                </For>
+            </ul>
+            <form on:submit=new class="flex flex-col gap-3">
+                <div class="flex gap-2">
+                    <InputWrap label="Kod" explanation="verksamhet eller ansvar">
+                        <input name="code" class="input input-bordered w-full max-w-xs" required/>
+                    </InputWrap>
+                    <InputWrap label="Namn" explanation="valfritt">
+                        <input name="name" class="input input-bordered w-full max-w-xs"/>
+                    </InputWrap>
+                </div>
+                <button type="submit" class="btn btn-secondary btn-outline">
+                    Lägg till kostnadsställe
+                </button>
+            </form>
+        </div>
+    }
+}
+
+#[component]
+pub fn EmployerRates() -> impl IntoView {
+    let (r_settings, w_settings) = use_settings();
+    let today = Local::now().date_naive().to_string();
+    let rates = Signal::derive(move || r_settings.with(|s| s.employer_rates.rates().to_vec()));
+    let tax_free = TAX_FREE
+        .rates()
+        .iter()
+        .map(|r| {
+            view! {
+                <li class="flex justify-between py-2 text-sm">
+                    <span>{r.valid_from.format("%Y-%m-%d").to_string()}</span>
+                    <span>{kronor(r.per_mil)} "/mil"</span>
+                </li>
+            }
+        })
+        .collect_view();
+    let new = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        if let Ok(rate) = Rate::from_event(&ev) {
+            w_settings.update(|s| s.employer_rates.add(rate));
+        }
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Milersättning</h2>
+            <div>
+                <h3 class="font-medium">Skattefri ersättning</h3>
+                <ul role="list" class="divide-y divide-gray-100">
+                    {tax_free}
+                </ul>
+            </div>
+            <div>
+                <h3 class="font-medium">Arbetsgivarens ersättning</h3>
+                <ul role="list" class="divide-y divide-gray-100">
+                    <For each=rates key=|r| r.valid_from let:rate>
+                        <li class="flex justify-between items-center py-2 text-sm">
+                            <span>{rate.valid_from.format("%Y-%m-%d").to_string()}</span>
+                            <span>{kronor(rate.per_mil)} "/mil"</span>
+                            <button
+                                class="btn btn-ghost btn-circle btn-sm text-secondary"
+                                on:click=move |_| {
+                                    w_settings.update(|s| s.employer_rates.remove(&rate.valid_from))
+                                }
+                            >
+
+                                <Icon class="size-5" icon=icondata::TiDeleteOutline/>
+                            </button>
+                        </li>
+                    </For>
+                </ul>
+            </div>
+            <form on:submit=new class="flex flex-col gap-3">
+                <div class="flex gap-2">
+                    <InputWrap label="Gäller från">
+                        <input
+                            name="valid_from"
+                            type="date"
+                            value=today
+                            class="input input-bordered w-full max-w-xs"
+                            required
+                        />
+                    </InputWrap>
+                    <InputWrap label="Ersättning" explanation="kronor per mil">
+                        <input
+                            name="per_mil"
+                            type="number"
+                            min=0
+                            step=0.01
+                            inputmode="decimal"
+                            class="input input-bordered w-full max-w-xs"
+                            required
+                        />
+                    </InputWrap>
+                </div>
+                <button type="submit" class="btn btn-secondary btn-outline">
+                    Lägg till ersättning
+                </button>
+            </form>
+        </div>
+    }
+}
+
+/// Settings for guessing pairs that are neither in the matrix nor driven before.
+#[component]
+pub fn Estimates() -> impl IntoView {
+    let (r_settings, w_settings) = use_settings();
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Uppskattade resor</h2>
+            <p class="text-sm">
+                Saknas en resa i avståndstabellen räknas den ut från fågelvägen mellan platsernas koordinater.
+                Koordinaterna anges under <A href="/abasku/places" class="link">Platser</A>.
+            </p>
+            <div class="flex gap-2">
+                <InputWrap label="Vägfaktor" explanation="väg genom fågelväg">
+                    <input
+                        type="number"
+                        min=1
+                        step=0.05
+                        inputmode="decimal"
+                        class="input input-bordered w-full max-w-xs"
+                        prop:value=move || r_settings.with(|s| s.winding_factor.to_string())
+                        on:change=move |ev| {
+                            if let Ok(factor) = event_target_value(&ev).parse::<f32>() {
+                                w_settings.update(|s| s.winding_factor = factor.max(1.));
+                            }
+                        }
+                    />
+
+                </InputWrap>
+                <InputWrap label="Medelhastighet" explanation="km/h">
+                    <input
+                        type="number"
+                        min=1
+                        step=1
+                        inputmode="numeric"
+                        class="input input-bordered w-full max-w-xs"
+                        prop:value=move || r_settings.with(|s| s.average_speed.to_string())
+                        on:change=move |ev| {
+                            if let Ok(speed) = event_target_value(&ev).parse::<f32>() {
+                                w_settings.update(|s| s.average_speed = speed.max(1.));
+                            }
+                        }
+                    />
+
+                </InputWrap>
+            </div>
+            <div class="form-control">
+                <label class="label cursor-pointer justify-start align-center gap-3">
+                    <input
+                        type="checkbox"
+                        class="toggle toggle-primary"
+                        prop:checked=move || r_settings.with(|s| s.compose_routes)
+                        on:change=move |ev| {
+                            w_settings.update(|s| s.compose_routes = event_target_checked(&ev))
+                        }
+                    />
+                    <span class="label-text">Räkna okända resor via kända platser</span>
+                </label>
+            </div>
+        </div>
+    }
+}
+
+/// How quickly old trips stop counting towards the favourites, and the ones pinned or hidden.
+#[component]
+pub fn FavoriteRanking() -> impl IntoView {
+    let (r_settings, w_settings) = use_settings();
+    let pinned = Signal::derive(move || r_settings.with(|s| s.favorites.pinned.clone()));
+    let hidden = Signal::derive(move || r_settings.with(|s| s.favorites.hidden.clone()));
+    let route_list =
+        move |routes: Signal<Vec<Route>>, label: &'static str, undo: fn(&mut Settings, Route)| {
+            view! {
+                <ul role="list" class="divide-y divide-gray-100">
+                    <For each=routes key=|r| r.clone() let:route>
+                        <li class="flex justify-between items-center py-2 text-sm">
+                            <span>{route.for_humans()}</span>
+                            <button
+                                class="btn btn-xs btn-ghost"
+                                on:click=move |_| w_settings.update(|s| undo(s, route.clone()))
+                            >
+                                {label}
+                            </button>
+                        </li>
+                    </For>
+                </ul>
+            }
+        };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Favoriter</h2>
+            <p class="text-sm">
+                Resor räknas mindre ju äldre de är, så att favoriterna följer det du kör nu.
+                Tur och retur och resor åt andra hållet räknas var för sig.
+            </p>
+            <div class="flex gap-2">
+                <InputWrap label="Halveringstid" explanation="dagar">
+                    <input
+                        type="number"
+                        min=1
+                        step=1
+                        inputmode="numeric"
+                        class="input input-bordered w-full max-w-xs"
+                        prop:value=move || r_settings.with(|s| s.favorites.half_life.to_string())
+                        on:change=move |ev| {
+                            if let Ok(days @ 1..) = event_target_value(&ev).parse() {
+                                w_settings.update(|s| s.favorites.half_life = days);
+                            }
+                        }
+                    />
+
+                </InputWrap>
+                <InputWrap label="Antal">
+                    <input
+                        type="number"
+                        min=1
+                        step=1
+                        inputmode="numeric"
+                        class="input input-bordered w-full max-w-xs"
+                        prop:value=move || r_settings.with(|s| s.favorites.shown.to_string())
+                        on:change=move |ev| {
+                            if let Ok(shown @ 1..) = event_target_value(&ev).parse() {
+                                w_settings.update(|s| s.favorites.shown = shown);
+                            }
+                        }
+                    />
+
+                </InputWrap>
+            </div>
+            <Show when=move || pinned.with(|p| !p.is_empty())>
+                <div>
+                    <h3 class="font-medium">Fästa</h3>
+                    {route_list(pinned, "Lossa", |s, r| s.favorites.toggle_pin(r))}
+                </div>
+            </Show>
+            <Show when=move || hidden.with(|h| !h.is_empty())>
+                <div>
+                    <h3 class="font-medium">Dolda</h3>
+                    {route_list(hidden, "Visa igen", |s, r| s.favorites.unhide(&r))}
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+/// Picks the report layout to start from and puts together new ones.
+#[component]
+pub fn ReportTemplates() -> impl IntoView {
+    let (r_settings, w_settings) = use_settings();
+    let templates = Signal::derive(move || r_settings.with(|s| s.templates()));
+    let own = Signal::derive(move || r_settings.with(|s| s.report_templates.clone()));
+    let chosen = move || r_settings.with(|s| s.template(&s.report_template).name);
+    let (r_name, w_name) = create_signal(String::new());
+    let (r_layout, w_layout) = create_signal(Layout::default());
+    let (r_columns, w_columns) = create_signal(vec![Column::Date, Column::Route, Column::Distance]);
+    let new = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let name = r_name.get_untracked().trim().to_owned();
+        let columns = r_columns.get_untracked();
+        if name.is_empty() || columns.is_empty() || report::is_built_in(&name) {
+            return;
+        }
+        let template = Template {
+            name: name.clone(),
+            layout: r_layout.get_untracked(),
+            // Always in the same order, whatever order they were ticked in.
+            columns: Column::ALL
+                .into_iter()
+                .filter(|c| columns.contains(c))
+                .collect(),
+        };
+        w_settings.update(|s| {
+            s.report_templates.retain(|t| t.name != name);
+            s.report_templates.push(template);
+        });
+        w_name(String::new());
+    };
+
+    view! {
+        <div class="bg-base-200 h-fit p-8 rounded-lg w-full max-w-md flex flex-col gap-6">
+            <h2 class="text-xl font-medium">Rapportmallar</h2>
+            <InputWrap label="Förvald mall">
+                <select
+                    class="select select-bordered w-full max-w-xs"
+                    on:change=move |ev| {
+                        w_settings.update(|s| s.report_template = event_target_value(&ev))
+                    }
+                >
+                    <For each=templates key=|t| t.name.clone() let:template>
+                        <option
+                            value=template.name.clone()
+                            selected={
+                                let name = template.name.clone();
+                                move || chosen() == name
+                            }
+                        >
+                            {template.name.clone()}
+                        </option>
+                    </For>
+                </select>
+            </InputWrap>
+            <ul role="list" class="divide-y divide-gray-100">
+                <For each=own key=|t| t.name.clone() let:template>
+                    <li class="flex justify-between items-center py-2 text-sm">
+                        <span>{template.name.clone()}</span>
+                        <span>{template.layout.for_humans()}</span>
+                        <button
+                            class="btn btn-ghost btn-circle btn-sm text-secondary"
+                            on:click=move |_| {
+                                w_settings
+                                    .update(|s| {
+                                        s.report_templates.retain(|t| t.name != template.name)
+                                    })
+                            }
+                        >
+                            <Icon class="size-5" icon=icondata::TiDeleteOutline/>
+                        </button>
+                    </li>
+                </For>
+            </ul>
+            <form on:submit=new class="flex flex-col gap-3">
+                <InputWrap
+                    label="Namn"
+                    error=Signal::derive(move || {
+                        if r_name.with(|n| report::is_built_in(n)) {
+                            "Namnet används redan av en inbyggd mall".to_owned()
+                        } else {
+                            String::new()
+                        }
+                    })
+                >
+
+                    <input
+                        class="input input-bordered w-full max-w-xs"
+                        prop:value=r_name
+                        on:input=move |ev| w_name(event_target_value(&ev))
+                        required
+                    />
+                </InputWrap>
+                <InputWrap label="Format">
+                    <select
+                        class="select select-bordered w-full max-w-xs"
+                        on:change=move |ev| {
+                            let picked = event_target_value(&ev).parse::<usize>();
+                            if let Some(layout) = picked.ok().and_then(|i| Layout::ALL.get(i)) {
+                                w_layout(*layout);
+                            }
+                        }
+                    >
+                        {Layout::ALL
+                            .iter()
+                            .enumerate()
+                            .map(|(i, l)| view! { <option value=i>{l.for_humans()}</option> })
+                            .collect_view()}
+                    </select>
+                </InputWrap>
+                <div class="flex flex-wrap gap-x-4">
+                    {Column::ALL
+                        .into_iter()
+                        .map(|column| {
+                            view! {
+                                <label class="label cursor-pointer justify-start gap-2">
+                                    <input
+                                        type="checkbox"
+                                        class="checkbox checkbox-sm"
+                                        prop:checked=move || r_columns.with(|c| c.contains(&column))
+                                        on:change=move |ev| {
+                                            let checked = event_target_checked(&ev);
+                                            w_columns.update(|c| {
+                                                c.retain(|x| *x != column);
+                                                if checked {
+                                                    c.push(column);
+                                                }
+                                            })
+                                        }
+                                    />
+                                    <span class="label-text">{column.for_humans()}</span>
+                                </label>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                <button type="submit" class="btn btn-secondary btn-outline">
+                    Spara mall
+                </button>
+            </form>
+        </div>
+    }
+}
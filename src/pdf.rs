@@ -0,0 +1,249 @@
+//! Just enough PDF to print a table of text: A4 pages, Helvetica and straight lines.
+
+use std::io::Write;
+
+pub const WIDTH: f32 = 595.;
+pub const HEIGHT: f32 = 842.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(&self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    content: Vec<u8>,
+}
+
+impl Page {
+    /// Writes `text` with its baseline starting at `(x, y)`, measured from the bottom left corner.
+    pub fn text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        _ = write!(
+            self.content,
+            "BT /{} {size} Tf {x:.2} {y:.2} Td (",
+            font.resource()
+        );
+        self.content.extend(encode(text));
+        self.content.extend_from_slice(b") Tj ET\n");
+    }
+    /// Like [`Page::text`], but with the text ending at `x`.
+    pub fn text_right(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.text(x - text_width(text, size), y, size, font, text);
+    }
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        _ = writeln!(self.content, "0.5 w {x1:.2} {y1:.2} m {x2:.2} {y2:.2} l S");
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pages: Vec<Page>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push(&mut self, page: Page) {
+        self.pages.push(page);
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Objects 1-4 are fixed, then a content stream and a page object per page.
+        let page_ids = (0..self.pages.len()).map(|i| 6 + 2 * i).collect::<Vec<_>>();
+        let kids = page_ids
+            .iter()
+            .map(|id| format!("{id} 0 R"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut objects: Vec<Vec<u8>> = vec![
+            b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+            format!(
+                "<< /Type /Pages /Kids [{kids}] /Count {} >>",
+                self.pages.len()
+            )
+            .into_bytes(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_vec(),
+        ];
+        for (page, id) in self.pages.iter().zip(&page_ids) {
+            let mut stream = format!("<< /Length {} >>\nstream\n", page.content.len()).into_bytes();
+            stream.extend_from_slice(&page.content);
+            stream.extend_from_slice(b"\nendstream");
+            objects.push(stream);
+            objects.push(
+                format!(
+                    "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {WIDTH} {HEIGHT}] \
+                     /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                    id - 1
+                )
+                .into_bytes(),
+            );
+        }
+
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            _ = writeln!(out, "{} 0 obj", i + 1);
+            out.extend_from_slice(object);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            _ = writeln!(out, "{offset:010} 00000 n ");
+        }
+        _ = write!(
+            out,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        );
+        out
+    }
+}
+
+/// Windows-1252 as expected by WinAnsiEncoding, with the string delimiters escaped.
+fn encode(text: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => out.extend_from_slice(&[b'\\', c as u8]),
+            '–' => out.push(0x96),
+            '—' => out.push(0x97),
+            '€' => out.push(0x80),
+            '→' => out.extend_from_slice(b"->"),
+            c if (c as u32) < 0x100 => out.push(c as u32 as u8),
+            _ => out.push(b'?'),
+        }
+    }
+    out
+}
+
+/// Approximate width in points, exact for digits and punctuation in Helvetica.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => 556,
+            ',' | '.' | ' ' | ':' | '/' => 278,
+            '-' => 333,
+            'i' | 'j' | 'l' | 'I' => 222,
+            'm' | 'w' | 'M' | 'W' => 833,
+            c if c.is_uppercase() => 667,
+            _ => 556,
+        })
+        .sum();
+    units as f32 / 1000. * size
+}
+
+/// Shortens `text` with an ellipsis until it fits in `width`.
+pub fn fit(text: &str, width: f32, size: f32) -> String {
+    if text_width(text, size) <= width {
+        return text.to_owned();
+    }
+    let mut fitted: String = text.to_owned();
+    while !fitted.is_empty() && text_width(&fitted, size) + text_width("...", size) > width {
+        fitted.pop();
+    }
+    fitted + "..."
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|w| w == needle)
+    }
+
+    fn document(pages: usize) -> Vec<u8> {
+        let mut document = Document::new();
+        for i in 0..pages {
+            let mut page = Page::default();
+            let title = format!("Sida {i} (av {pages})");
+            page.text(50., 700., 12., Font::Bold, &title);
+            page.line(50., 690., 545., 690.);
+            document.push(page);
+        }
+        document.to_bytes()
+    }
+
+    #[test]
+    fn the_xref_table_points_at_every_object() {
+        let pdf = document(3);
+        let text = String::from_utf8_lossy(&pdf);
+        let startxref = text.rsplit("startxref\n").next().unwrap();
+        let xref: usize = startxref.lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with(b"xref\n"));
+
+        let table = String::from_utf8_lossy(&pdf[xref..]);
+        let mut lines = table.lines().skip(1);
+        let size: usize = lines.next().unwrap()["0 ".len()..].parse().unwrap();
+        // Catalog, page tree, two fonts, and a content stream and a page for each page.
+        assert_eq!(size, 1 + 4 + 2 * 3);
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for id in 1..size {
+            let entry = lines.next().unwrap();
+            assert!(entry.ends_with(" 00000 n "), "{entry}");
+            let offset: usize = entry[..10].parse().unwrap();
+            let header = format!("{id} 0 obj\n");
+            assert!(pdf[offset..].starts_with(header.as_bytes()), "object {id}");
+        }
+        assert!(text.contains(&format!("/Size {size} /Root 1 0 R")));
+        assert!(text.ends_with("%%EOF\n"));
+    }
+
+    #[test]
+    fn stream_lengths_match_their_content() {
+        let pdf = document(1);
+        let start = position(&pdf, b"<< /Length ").unwrap() + b"<< /Length ".len();
+        let end = start + position(&pdf[start..], b" >>").unwrap();
+        let length = String::from_utf8_lossy(&pdf[start..end]);
+        let length: usize = length.parse().unwrap();
+        let content = end + b" >>\nstream\n".len();
+        assert!(pdf[content + length..].starts_with(b"\nendstream"));
+    }
+
+    #[test]
+    fn swedish_letters_are_single_winansi_bytes() {
+        assert_eq!(encode("åäö ÅÄÖ"), b"\xe5\xe4\xf6 \xc5\xc4\xd6");
+        assert_eq!(encode("3–5 € – →"), b"3\x965 \x80 \x96 ->");
+        assert_eq!(encode("(a\\b)"), b"\\(a\\\\b\\)");
+    }
+
+    #[test]
+    fn characters_outside_winansi_become_question_marks() {
+        assert_eq!(encode("Łódź"), b"?\xf3d?");
+        assert_eq!(encode("🚗 ok"), b"? ok");
+    }
+
+    #[test]
+    fn widths_add_up_per_character() {
+        assert_eq!(text_width("10", 10.), 11.12);
+        assert_eq!(text_width("", 10.), 0.);
+        assert!(text_width("MW", 10.) > text_width("il", 10.));
+    }
+
+    #[test]
+    fn fit_keeps_short_text_and_cuts_long_text_with_an_ellipsis() {
+        assert_eq!(fit("Kommunhuset", 100., 9.), "Kommunhuset");
+        let fitted = fit("Kommunhuset-Ekenässkolan-Platengymnasiet", 100., 9.);
+        assert!(fitted.ends_with("..."));
+        assert!(fitted.starts_with("Kommunhuset"));
+        assert!(text_width(&fitted, 9.) <= 100.);
+        // Not even one character fits beside the ellipsis.
+        assert_eq!(fit("Kommunhuset", 5., 9.), "...");
+    }
+}
@@ -0,0 +1,230 @@
+use chrono::{Datelike, Months, NaiveDate};
+
+/// A stretch of days that trips are summed up and reported for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Period {
+    Month {
+        year: i32,
+        month: u32,
+    },
+    Quarter {
+        year: i32,
+        quarter: u32,
+    },
+    /// A calendar year, as asked for in the deklaration.
+    Year(i32),
+    /// Any two days, both included.
+    Range {
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+}
+
+impl Period {
+    pub fn month(year: i32, month: u32) -> Option<Self> {
+        (1..=12)
+            .contains(&month)
+            .then_some(Self::Month { year, month })
+    }
+    pub fn quarter(year: i32, quarter: u32) -> Option<Self> {
+        (1..=4)
+            .contains(&quarter)
+            .then_some(Self::Quarter { year, quarter })
+    }
+    pub fn range(from: NaiveDate, to: NaiveDate) -> Self {
+        Self::Range {
+            from: from.min(to),
+            to: from.max(to),
+        }
+    }
+    /// The month before the one `today` is in, which is what is usually reported.
+    pub fn last_month(today: NaiveDate) -> Self {
+        let then = today.checked_sub_months(Months::new(1)).unwrap_or(today);
+        Self::Month {
+            year: then.year(),
+            month: then.month(),
+        }
+    }
+
+    pub fn first_of(&self) -> NaiveDate {
+        match *self {
+            Self::Month { year, month } => NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+            Self::Quarter { year, quarter } => {
+                NaiveDate::from_ymd_opt(year, (quarter - 1) * 3 + 1, 1).unwrap()
+            }
+            Self::Year(year) => NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            Self::Range { from, .. } => from,
+        }
+    }
+    pub fn last_of(&self) -> NaiveDate {
+        let months = match *self {
+            Self::Month { .. } => 1,
+            Self::Quarter { .. } => 3,
+            Self::Year(_) => 12,
+            Self::Range { to, .. } => return to,
+        };
+        self.first_of()
+            .checked_add_months(Months::new(months))
+            .and_then(|next| next.pred_opt())
+            .unwrap()
+    }
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.first_of() <= date && date <= self.last_of()
+    }
+
+    pub fn fmt_human(&self) -> String {
+        match self {
+            Self::Month { year, month } => format!("{} {year}", month_name(*month)),
+            Self::Quarter { year, quarter } => format!("kvartal {quarter} {year}"),
+            Self::Year(year) => format!("helår {year}"),
+            Self::Range { from, to } => format!("{from} – {to}"),
+        }
+    }
+    /// Goes into the names of downloaded files.
+    pub fn slug(&self) -> String {
+        match self {
+            Self::Month { year, month } => format!("{year}-{month:02}"),
+            Self::Quarter { year, quarter } => format!("{year}-k{quarter}"),
+            Self::Year(year) => year.to_string(),
+            Self::Range { from, to } => format!("{from}--{to}"),
+        }
+    }
+    /// Where the report is, relative to the checkpoint page.
+    pub fn path(&self) -> String {
+        match self {
+            Self::Month { year, month } => format!("report/{year}/{month}"),
+            Self::Quarter { year, quarter } => format!("report/{year}/quarter/{quarter}"),
+            Self::Year(year) => format!("report/{year}"),
+            Self::Range { from, to } => format!("report/range/{from}/{to}"),
+        }
+    }
+}
+
+fn month_name(month: u32) -> &'static str {
+    match month {
+        1 => "januari",
+        2 => "februari",
+        3 => "mars",
+        4 => "april",
+        5 => "maj",
+        6 => "juni",
+        7 => "juli",
+        8 => "augusti",
+        9 => "september",
+        10 => "oktober",
+        11 => "november",
+        12 => "december",
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn refuses_months_and_quarters_that_do_not_exist() {
+        assert_eq!(Period::month(2024, 0), None);
+        assert_eq!(Period::month(2024, 13), None);
+        assert_eq!(Period::quarter(2024, 0), None);
+        assert_eq!(Period::quarter(2024, 5), None);
+        assert!(Period::month(2024, 12).is_some());
+        assert!(Period::quarter(2024, 4).is_some());
+    }
+
+    #[test]
+    fn months_end_on_their_last_day() {
+        let bounds = |month| {
+            let p = Period::month(2023, month).unwrap();
+            (p.first_of(), p.last_of())
+        };
+        assert_eq!(bounds(1), (day(2023, 1, 1), day(2023, 1, 31)));
+        assert_eq!(bounds(4), (day(2023, 4, 1), day(2023, 4, 30)));
+        assert_eq!(bounds(12), (day(2023, 12, 1), day(2023, 12, 31)));
+    }
+
+    #[test]
+    fn february_knows_about_leap_years() {
+        assert_eq!(Period::month(2024, 2).unwrap().last_of(), day(2024, 2, 29));
+        assert_eq!(Period::month(2023, 2).unwrap().last_of(), day(2023, 2, 28));
+        assert_eq!(Period::month(2100, 2).unwrap().last_of(), day(2100, 2, 28));
+        assert_eq!(Period::month(2000, 2).unwrap().last_of(), day(2000, 2, 29));
+    }
+
+    #[test]
+    fn quarters_and_years_cover_their_months() {
+        let q1 = Period::quarter(2024, 1).unwrap();
+        assert_eq!(
+            (q1.first_of(), q1.last_of()),
+            (day(2024, 1, 1), day(2024, 3, 31))
+        );
+        let q4 = Period::quarter(2024, 4).unwrap();
+        assert_eq!(
+            (q4.first_of(), q4.last_of()),
+            (day(2024, 10, 1), day(2024, 12, 31))
+        );
+        let year = Period::Year(2024);
+        assert_eq!(
+            (year.first_of(), year.last_of()),
+            (day(2024, 1, 1), day(2024, 12, 31))
+        );
+    }
+
+    #[test]
+    fn contains_both_ends_and_nothing_beyond() {
+        let q2 = Period::quarter(2024, 2).unwrap();
+        assert!(!q2.contains(day(2024, 3, 31)));
+        assert!(q2.contains(day(2024, 4, 1)));
+        assert!(q2.contains(day(2024, 6, 30)));
+        assert!(!q2.contains(day(2024, 7, 1)));
+    }
+
+    #[test]
+    fn ranges_read_the_same_either_way_round() {
+        let range = Period::range(day(2025, 1, 5), day(2024, 12, 28));
+        assert_eq!(range, Period::range(day(2024, 12, 28), day(2025, 1, 5)));
+        assert_eq!(range.first_of(), day(2024, 12, 28));
+        assert_eq!(range.last_of(), day(2025, 1, 5));
+        assert!(range.contains(day(2024, 12, 31)));
+        assert!(range.contains(day(2025, 1, 1)));
+        let single = Period::range(day(2024, 2, 29), day(2024, 2, 29));
+        assert!(single.contains(day(2024, 2, 29)));
+        assert!(!single.contains(day(2024, 3, 1)));
+    }
+
+    #[test]
+    fn last_month_crosses_into_the_previous_year() {
+        assert_eq!(
+            Period::last_month(day(2024, 1, 15)),
+            Period::month(2023, 12).unwrap()
+        );
+        assert_eq!(
+            Period::last_month(day(2024, 3, 31)),
+            Period::month(2024, 2).unwrap()
+        );
+    }
+
+    #[test]
+    fn labels_slugs_and_paths() {
+        let month = Period::month(2024, 3).unwrap();
+        assert_eq!(month.fmt_human(), "mars 2024");
+        assert_eq!(month.slug(), "2024-03");
+        assert_eq!(month.path(), "report/2024/3");
+        let quarter = Period::quarter(2024, 2).unwrap();
+        assert_eq!(quarter.fmt_human(), "kvartal 2 2024");
+        assert_eq!(quarter.slug(), "2024-k2");
+        assert_eq!(quarter.path(), "report/2024/quarter/2");
+        let year = Period::Year(2024);
+        assert_eq!(year.fmt_human(), "helår 2024");
+        assert_eq!(year.slug(), "2024");
+        assert_eq!(year.path(), "report/2024");
+        let range = Period::range(day(2024, 12, 30), day(2025, 1, 3));
+        assert_eq!(range.fmt_human(), "2024-12-30 – 2025-01-03");
+        assert_eq!(range.slug(), "2024-12-30--2025-01-03");
+        assert_eq!(range.path(), "report/range/2024-12-30/2025-01-03");
+    }
+}
@@ -0,0 +1,202 @@
+use std::ops::Add;
+
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::Trip;
+
+/// A rate in kronor per mil (10 km), valid from a given date until the next rate takes over.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Rate {
+    pub valid_from: NaiveDate,
+    pub per_mil: f32,
+}
+
+impl Rate {
+    pub fn new(valid_from: NaiveDate, per_mil: f32) -> Self {
+        Self {
+            valid_from,
+            per_mil,
+        }
+    }
+}
+
+/// Rates versioned by date, so that older trips are paid with the rate that was valid then.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct RateTable {
+    rates: Vec<Rate>,
+}
+
+impl RateTable {
+    pub fn new(mut rates: Vec<Rate>) -> Self {
+        rates.sort_by_key(|r| r.valid_from);
+        Self { rates }
+    }
+    pub fn add(&mut self, rate: Rate) {
+        self.rates.retain(|r| r.valid_from != rate.valid_from);
+        self.rates.push(rate);
+        self.rates.sort_by_key(|r| r.valid_from);
+    }
+    pub fn remove(&mut self, valid_from: &NaiveDate) {
+        self.rates.retain(|r| r.valid_from != *valid_from);
+    }
+    pub fn rates(&self) -> &[Rate] {
+        &self.rates
+    }
+    pub fn rate_on(&self, date: NaiveDate) -> Option<f32> {
+        self.rates
+            .iter()
+            .rev()
+            .find(|r| r.valid_from <= date)
+            .map(|r| r.per_mil)
+    }
+}
+
+/// Skatteverket's tax-free rate for driving your own car in service.
+pub static TAX_FREE: Lazy<RateTable> = Lazy::new(|| {
+    RateTable::new(vec![
+        Rate::new(NaiveDate::from_ymd_opt(2007, 1, 1).unwrap(), 18.5),
+        Rate::new(NaiveDate::from_ymd_opt(2023, 7, 1).unwrap(), 25.),
+    ])
+});
+
+/// The money claim for one or more trips, split in a tax-free and a taxable part.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Reimbursement {
+    pub tax_free: f32,
+    pub taxable: f32,
+}
+
+impl Reimbursement {
+    /// Pays `employer` per mil if there is an employer rate for the date of the trip, otherwise
+    /// the tax-free rate. Whatever exceeds the tax-free rate is taxable.
+    pub fn for_trip(trip: &Trip, employer: &RateTable) -> Self {
+        let mil = trip.calculate_distance() / 10.;
+        let tax_free_rate = TAX_FREE.rate_on(trip.date).unwrap_or(0.);
+        let employer_rate = employer.rate_on(trip.date).unwrap_or(tax_free_rate);
+        let tax_free = mil * tax_free_rate.min(employer_rate);
+        let taxable = mil * employer_rate - tax_free;
+        Self { tax_free, taxable }
+    }
+    pub fn for_trips<'a>(trips: impl IntoIterator<Item = &'a Trip>, employer: &RateTable) -> Self {
+        trips
+            .into_iter()
+            .map(|t| Self::for_trip(t, employer))
+            .fold(Self::default(), Add::add)
+    }
+    pub fn total(&self) -> f32 {
+        self.tax_free + self.taxable
+    }
+}
+
+impl Add for Reimbursement {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            tax_free: self.tax_free + rhs.tax_free,
+            taxable: self.taxable + rhs.taxable,
+        }
+    }
+}
+
+pub fn kronor(amount: f32) -> String {
+    format!("{amount:.2} kr").replace('.', ",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_trip;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn trip(date: NaiveDate, distance: f32) -> Trip {
+        Trip {
+            date,
+            distance,
+            ..test_trip()
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn rates_take_over_on_their_first_day() {
+        assert_eq!(TAX_FREE.rate_on(day(2006, 12, 31)), None);
+        assert_eq!(TAX_FREE.rate_on(day(2007, 1, 1)), Some(18.5));
+        assert_eq!(TAX_FREE.rate_on(day(2023, 6, 30)), Some(18.5));
+        assert_eq!(TAX_FREE.rate_on(day(2023, 7, 1)), Some(25.));
+        assert_eq!(TAX_FREE.rate_on(day(2030, 1, 1)), Some(25.));
+    }
+
+    #[test]
+    fn adding_a_rate_for_the_same_day_replaces_it_and_keeps_the_order() {
+        let mut table = RateTable::new(vec![
+            Rate::new(day(2024, 1, 1), 30.),
+            Rate::new(day(2023, 1, 1), 28.),
+        ]);
+        table.add(Rate::new(day(2023, 1, 1), 29.));
+        table.add(Rate::new(day(2023, 7, 1), 29.5));
+        let starts = table
+            .rates()
+            .iter()
+            .map(|r| r.valid_from)
+            .collect::<Vec<_>>();
+        assert_eq!(starts, [day(2023, 1, 1), day(2023, 7, 1), day(2024, 1, 1)]);
+        assert_eq!(table.rate_on(day(2023, 3, 1)), Some(29.));
+        table.remove(&day(2023, 7, 1));
+        assert_eq!(table.rate_on(day(2023, 8, 1)), Some(29.));
+    }
+
+    #[test]
+    fn without_an_employer_rate_everything_is_tax_free() {
+        let paid = Reimbursement::for_trip(&trip(day(2024, 3, 1), 20.), &RateTable::default());
+        assert!(close(paid.tax_free, 50.));
+        assert!(close(paid.taxable, 0.));
+    }
+
+    #[test]
+    fn an_employer_rate_above_the_tax_free_one_is_taxed_on_the_excess() {
+        let employer = RateTable::new(vec![Rate::new(day(2024, 1, 1), 30.)]);
+        let paid = Reimbursement::for_trip(&trip(day(2024, 3, 1), 20.), &employer);
+        assert!(close(paid.tax_free, 50.));
+        assert!(close(paid.taxable, 10.));
+        assert!(close(paid.total(), 60.));
+    }
+
+    #[test]
+    fn an_employer_rate_below_the_tax_free_one_is_all_tax_free() {
+        let employer = RateTable::new(vec![Rate::new(day(2024, 1, 1), 20.)]);
+        let paid = Reimbursement::for_trip(&trip(day(2024, 3, 1), 20.), &employer);
+        assert!(close(paid.tax_free, 40.));
+        assert!(close(paid.taxable, 0.));
+    }
+
+    #[test]
+    fn trips_are_paid_with_the_rates_of_their_own_day() {
+        let employer = RateTable::new(vec![Rate::new(day(2024, 1, 1), 30.)]);
+        let trips = [
+            // Before the employer rate, so paid at the tax-free rate of the time.
+            trip(day(2023, 6, 30), 10.),
+            Trip {
+                returning: true,
+                ..trip(day(2024, 1, 1), 10.)
+            },
+        ];
+        let paid = Reimbursement::for_trips(&trips, &employer);
+        assert!(close(paid.tax_free, 18.5 + 50.));
+        assert!(close(paid.taxable, 10.));
+    }
+
+    #[test]
+    fn kronor_use_a_decimal_comma() {
+        assert_eq!(kronor(1234.5), "1234,50 kr");
+    }
+}
@@ -0,0 +1,386 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    reimbursement::{kronor, RateTable, Reimbursement},
+    vehicles::Vehicles,
+    Trip,
+};
+
+/// One piece of information about a trip that a report can show.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    Date,
+    Route,
+    Distance,
+    Time,
+    Reason,
+    Vehicle,
+    Odometer,
+    Reimbursement,
+    CostCentre,
+}
+
+impl Column {
+    pub const ALL: [Column; 9] = [
+        Self::Date,
+        Self::Route,
+        Self::Distance,
+        Self::Time,
+        Self::Reason,
+        Self::Vehicle,
+        Self::Odometer,
+        Self::Reimbursement,
+        Self::CostCentre,
+    ];
+
+    pub fn for_humans(&self) -> &'static str {
+        match self {
+            Self::Date => "Datum",
+            Self::Route => "Resa",
+            Self::Distance => "Avstånd",
+            Self::Time => "Restid",
+            Self::Reason => "Ärende",
+            Self::Vehicle => "Fordon",
+            Self::Odometer => "Mätarställning",
+            Self::Reimbursement => "Ersättning",
+            Self::CostCentre => "Kostnadsställe",
+        }
+    }
+}
+
+/// How the columns are put together into text.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// Lined up with dots, the way the log has always been copied.
+    #[default]
+    Dotted,
+    /// Tab-separated with a header, for pasting into Excel.
+    Tabs,
+    /// A Markdown table.
+    Markdown,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 3] = [Self::Dotted, Self::Tabs, Self::Markdown];
+
+    pub fn for_humans(&self) -> &'static str {
+        match self {
+            Self::Dotted => "Punktad text",
+            Self::Tabs => "Tabbar (Excel)",
+            Self::Markdown => "Markdown-tabell",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub layout: Layout,
+    pub columns: Vec<Column>,
+}
+
+impl Template {
+    pub fn new(name: &str, layout: Layout, columns: &[Column]) -> Self {
+        Self {
+            name: name.to_owned(),
+            layout,
+            columns: columns.to_vec(),
+        }
+    }
+}
+
+/// Whether `name` is taken by one of the templates everyone has.
+pub fn is_built_in(name: &str) -> bool {
+    built_in()
+        .iter()
+        .any(|t| t.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// The templates everyone has, ahead of the ones the user made.
+pub fn built_in() -> Vec<Template> {
+    use Column::*;
+    vec![
+        Template::new("Standard", Layout::Dotted, &[Date, Route, Distance, Reason]),
+        Template::new(
+            "Med ersättning",
+            Layout::Dotted,
+            &[Date, Route, Distance, Reason, Reimbursement],
+        ),
+        Template::new(
+            "Excel",
+            Layout::Tabs,
+            &[Date, Route, Distance, Time, Reason],
+        ),
+        Template::new(
+            "Markdown",
+            Layout::Markdown,
+            &[Date, Route, Distance, Time, Reason, Reimbursement],
+        ),
+    ]
+}
+
+/// What a report needs to know besides the trips themselves.
+pub struct Context<'a> {
+    pub vehicles: &'a Vehicles,
+    pub rates: &'a RateTable,
+}
+
+impl Context<'_> {
+    /// Tabs and line breaks from free text would split the row, so they become spaces.
+    fn cell(&self, column: Column, layout: Layout, trip: &Trip) -> String {
+        let text = match column {
+            Column::Date if layout == Layout::Dotted => trip.date.format("%d/%m").to_string(),
+            Column::Date => trip.date.format("%Y-%m-%d").to_string(),
+            Column::Route => trip.from_to(),
+            // Excel wants a bare number to be able to sum the column.
+            Column::Distance if layout == Layout::Tabs => {
+                trip.calculate_distance().to_string().replace('.', ",")
+            }
+            Column::Distance => trip.distance_for_human(),
+            Column::Time => format!("{} min", trip.calculate_time()),
+            Column::Reason => trip.reason.clone(),
+            Column::Vehicle => self.vehicles.name_of(trip),
+            Column::Odometer => trip
+                .odometer
+                .map(|o| format!("{}–{}", o.start, o.end))
+                .unwrap_or_default(),
+            Column::Reimbursement if self.vehicles.reimbursable(trip) => {
+                kronor(Reimbursement::for_trip(trip, self.rates).total())
+            }
+            Column::Reimbursement => "–".to_owned(),
+            Column::CostCentre => trip.cost_centre.clone().unwrap_or_default(),
+        };
+        text.replace("\r\n", " ").replace(['\t', '\n', '\r'], " ")
+    }
+
+    /// The trips laid out by `template`, followed by the summary lines.
+    pub fn render(&self, template: &Template, trips: &[Trip], summary: &[String]) -> String {
+        let columns = &template.columns;
+        let rows = trips
+            .iter()
+            .map(|t| {
+                columns
+                    .iter()
+                    .map(|c| self.cell(*c, template.layout, t))
+                    .collect_vec()
+            })
+            .collect_vec();
+        let header = columns
+            .iter()
+            .map(|c| c.for_humans().to_owned())
+            .collect_vec();
+        let mut lines = match template.layout {
+            Layout::Dotted => dotted(columns, &rows),
+            Layout::Tabs => std::iter::once(header.join("\t"))
+                .chain(rows.iter().map(|r| r.join("\t")))
+                .collect(),
+            Layout::Markdown => {
+                let row = |cells: &[String]| format!("| {} |", cells.join(" | "));
+                let rule = vec!["---".to_owned(); columns.len()];
+                [row(&header), row(&rule)]
+                    .into_iter()
+                    .chain(
+                        rows.iter()
+                            .map(|r| row(&r.iter().map(|c| c.replace('|', "\\|")).collect_vec())),
+                    )
+                    .collect()
+            }
+        };
+        lines.push(String::new());
+        lines.extend(summary.iter().cloned());
+        lines.join("\n")
+    }
+}
+
+/// Every column but the last is padded with dots to three past the widest, and a leading date is
+/// followed by a colon instead.
+fn dotted(columns: &[Column], rows: &[Vec<String>]) -> Vec<String> {
+    let widths = (0..columns.len())
+        .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+        .collect_vec();
+    rows.iter()
+        .map(|row| {
+            let last = row.len().saturating_sub(1);
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    if i == 0 && columns[0] == Column::Date && last > 0 {
+                        format!("{cell}: ")
+                    } else if i < last {
+                        format!("{cell}{}", ".".repeat(3 + widths[i] - cell.chars().count()))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        settings::Settings,
+        test_trip,
+        vehicles::{Ownership, Vehicle},
+    };
+
+    fn trip(day: u32, to: &str, distance: f32, reason: &str) -> Trip {
+        Trip {
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            to: to.into(),
+            distance,
+            time: 15,
+            reason: reason.into(),
+            ..test_trip()
+        }
+    }
+
+    fn trips() -> [Trip; 2] {
+        [
+            trip(5, "Skolan", 3.2, "Möte"),
+            Trip {
+                returning: true,
+                ..trip(12, "Ekenässkolan", 10., "Kurs")
+            },
+        ]
+    }
+
+    fn render(template: &Template, trips: &[Trip]) -> String {
+        let context = Context {
+            vehicles: &Vehicles::default(),
+            rates: &RateTable::default(),
+        };
+        context.render(template, trips, &["Totalt: 23,2 km".to_owned()])
+    }
+
+    #[test]
+    fn built_in_templates_have_distinct_names_and_columns() {
+        let templates = built_in();
+        assert_eq!(templates[0].name, "Standard");
+        assert!(templates.iter().map(|t| &t.name).all_unique());
+        assert!(templates.iter().all(|t| !t.columns.is_empty()));
+    }
+
+    #[test]
+    fn built_in_names_are_taken_in_any_case() {
+        assert!(is_built_in("Standard"));
+        assert!(is_built_in(" excel "));
+        assert!(!is_built_in("Standardmall"));
+        assert!(!is_built_in("Min mall"));
+    }
+
+    #[test]
+    fn own_templates_named_like_built_in_ones_are_left_out() {
+        let settings = Settings {
+            report_templates: vec![
+                Template::new("excel", Layout::Markdown, &[Column::Date]),
+                Template::new("Min mall", Layout::Tabs, &[Column::Reason]),
+            ],
+            ..Default::default()
+        };
+        let names = settings
+            .templates()
+            .into_iter()
+            .map(|t| t.name)
+            .collect_vec();
+        assert_eq!(
+            names,
+            [
+                "Standard",
+                "Med ersättning",
+                "Excel",
+                "Markdown",
+                "Min mall"
+            ]
+        );
+        assert_eq!(settings.template("Excel").layout, Layout::Tabs);
+        assert_eq!(settings.template("Finns inte").name, "Standard");
+    }
+
+    #[test]
+    fn dotted_lines_up_every_column_but_the_last() {
+        let expected = [
+            "05/03: Kommunhuset-Skolan.............3,2 km...Möte",
+            "12/03: Kommunhuset-Ekenässkolan ToR...20 km....Kurs",
+            "",
+            "Totalt: 23,2 km",
+        ];
+        assert_eq!(render(&built_in()[0], &trips()), expected.join("\n"));
+    }
+
+    #[test]
+    fn tabs_have_a_header_and_bare_numbers() {
+        let excel = Template::new(
+            "Excel",
+            Layout::Tabs,
+            &[Column::Date, Column::Distance, Column::Time],
+        );
+        let lines = render(&excel, &trips());
+        let lines = lines.lines().collect_vec();
+        assert_eq!(lines[0], "Datum\tAvstånd\tRestid");
+        assert_eq!(lines[1], "2024-03-05\t3,2\t15 min");
+        // A return trip counts both ways, in distance as well as in time.
+        assert_eq!(lines[2], "2024-03-12\t20\t30 min");
+    }
+
+    #[test]
+    fn markdown_escapes_pipes_in_cells() {
+        let markdown = Template::new(
+            "Markdown",
+            Layout::Markdown,
+            &[Column::Route, Column::Reason],
+        );
+        let lines = render(&markdown, &[trip(5, "Skolan", 3.2, "Möte | lunch")]);
+        let lines = lines.lines().collect_vec();
+        assert_eq!(lines[0], "| Resa | Ärende |");
+        assert_eq!(lines[1], "| --- | --- |");
+        assert_eq!(lines[2], "| Kommunhuset-Skolan | Möte \\| lunch |");
+    }
+
+    #[test]
+    fn tabs_and_line_breaks_stay_inside_their_cell() {
+        let trips = [trip(5, "Skolan", 3.2, "Möte\tom budget\r\noch lunch")];
+        for (layout, row, expected) in [
+            (Layout::Tabs, 1, "Möte om budget och lunch\t3,2"),
+            (Layout::Markdown, 2, "| Möte om budget och lunch | 3,2 km |"),
+        ] {
+            let template = Template::new("Rader", layout, &[Column::Reason, Column::Distance]);
+            assert_eq!(render(&template, &trips).lines().nth(row), Some(expected));
+        }
+    }
+
+    #[test]
+    fn pool_cars_get_no_reimbursement_column_value() {
+        let mut vehicles = Vehicles::default();
+        vehicles.add(Vehicle {
+            id: Uuid::from_u128(1),
+            registration: "POOL1".into(),
+            ownership: Ownership::Pool,
+            ..Default::default()
+        });
+        let context = Context {
+            vehicles: &vehicles,
+            rates: &RateTable::default(),
+        };
+        let own = trip(5, "Skolan", 20., "Möte");
+        let pooled = Trip {
+            vehicle: Some(Uuid::from_u128(1)),
+            ..own.clone()
+        };
+        let template = Template::new(
+            "Test",
+            Layout::Tabs,
+            &[Column::Reimbursement, Column::Vehicle],
+        );
+        let lines = context.render(&template, &[own, pooled], &[]);
+        let lines = lines.lines().collect_vec();
+        assert_eq!(lines[1], "50,00 kr\tInget fordon");
+        assert_eq!(lines[2], "–\tPOOL1");
+    }
+}
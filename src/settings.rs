@@ -0,0 +1,106 @@
+use leptos::*;
+use leptos_use::{storage::use_local_storage, utils::JsonCodec};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    favorites::Favorites,
+    reimbursement::RateTable,
+    report::{self, Template},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// What the employer pays per mil. Falls back to the tax-free rate when empty.
+    #[serde(default)]
+    pub employer_rates: RateTable,
+    /// Printed in the header of the körjournal.
+    #[serde(default)]
+    pub employee: String,
+    /// Log odometer readings for each trip instead of typing the distance.
+    #[serde(default)]
+    pub odometer: bool,
+    /// How long deleted trips stay in the trash before they are gone for good.
+    #[serde(default = "default_trash_days")]
+    pub trash_days: u32,
+    /// How much longer the road is than the straight line, for estimated trips.
+    #[serde(default = "default_winding_factor")]
+    pub winding_factor: f32,
+    /// Kilometres per hour, for estimated trips.
+    #[serde(default = "default_average_speed")]
+    pub average_speed: f32,
+    /// Bridge pairs nobody knows through a place both ends are known from.
+    #[serde(default)]
+    pub compose_routes: bool,
+    /// Address of a sync server, see `src/bin/sync-server.rs`. Empty when not syncing.
+    #[serde(default)]
+    pub sync_url: String,
+    /// Sent as a bearer token if the server requires one.
+    #[serde(default)]
+    pub sync_token: String,
+    /// Name of the template the report opens with.
+    #[serde(default)]
+    pub report_template: String,
+    /// Templates the user put together, offered after the built-in ones.
+    #[serde(default)]
+    pub report_templates: Vec<Template>,
+    /// How the favourites on the home page are ranked, pinned and hidden.
+    #[serde(default)]
+    pub favorites: Favorites,
+}
+
+fn default_trash_days() -> u32 {
+    30
+}
+
+fn default_winding_factor() -> f32 {
+    1.3
+}
+
+fn default_average_speed() -> f32 {
+    60.
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            employer_rates: RateTable::default(),
+            employee: String::new(),
+            odometer: false,
+            trash_days: default_trash_days(),
+            winding_factor: default_winding_factor(),
+            average_speed: default_average_speed(),
+            compose_routes: false,
+            sync_url: String::new(),
+            sync_token: String::new(),
+            report_template: String::new(),
+            report_templates: Vec::new(),
+            favorites: Favorites::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Own templates saved under a built-in name before that was refused are left out.
+    pub fn templates(&self) -> Vec<Template> {
+        report::built_in()
+            .into_iter()
+            .chain(
+                self.report_templates
+                    .iter()
+                    .filter(|t| !report::is_built_in(&t.name))
+                    .cloned(),
+            )
+            .collect()
+    }
+    /// The template called `name`, or the first built-in one if there is none.
+    pub fn template(&self, name: &str) -> Template {
+        let mut templates = self.templates();
+        let found = templates.iter().position(|t| t.name == name).unwrap_or(0);
+        templates.swap_remove(found)
+    }
+}
+
+pub fn use_settings() -> (Signal<Settings>, WriteSignal<Settings>) {
+    let (r_settings, w_settings, _) = use_local_storage::<Settings, JsonCodec>("my-settings");
+    (r_settings, w_settings)
+}
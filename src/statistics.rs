@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use chrono::Datelike;
+use itertools::Itertools;
+
+use crate::Trip;
+
+/// Kilometres and hours for each month of a year, January first.
+pub fn per_month<'a>(trips: impl IntoIterator<Item = &'a Trip>, year: i32) -> [(f32, f32); 12] {
+    let mut months = [(0., 0.); 12];
+    for trip in trips.into_iter().filter(|t| t.date.year() == year) {
+        let (distance, hours) = &mut months[trip.date.month0() as usize];
+        *distance += trip.calculate_distance();
+        *hours += trip.calculate_time() as f32 / 60.;
+    }
+    months
+}
+
+/// How many trips there are on each weekday, Monday first.
+pub fn per_weekday<'a>(trips: impl IntoIterator<Item = &'a Trip>) -> [usize; 7] {
+    let mut days = [0; 7];
+    for trip in trips {
+        days[trip.date.weekday().num_days_from_monday() as usize] += 1;
+    }
+    days
+}
+
+/// The `n` most common values of `key`, most common first and ties in alphabetical order.
+fn top<'a>(
+    trips: impl IntoIterator<Item = &'a Trip>,
+    n: usize,
+    key: impl Fn(&Trip) -> String,
+) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for trip in trips {
+        *counts.entry(key(trip)).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+        .take(n)
+        .collect()
+}
+
+pub fn top_routes<'a>(trips: impl IntoIterator<Item = &'a Trip>, n: usize) -> Vec<(String, usize)> {
+    top(trips, n, Trip::from_to)
+}
+
+pub fn top_reasons<'a>(
+    trips: impl IntoIterator<Item = &'a Trip>,
+    n: usize,
+) -> Vec<(String, usize)> {
+    top(trips, n, |t| t.reason.trim().to_owned())
+}
+
+/// Mean kilometres per trip, counting a return trip as one.
+pub fn average_distance<'a>(trips: impl IntoIterator<Item = &'a Trip>) -> Option<f32> {
+    let (count, total) = trips
+        .into_iter()
+        .fold((0, 0.), |(n, km), t| (n + 1, km + t.calculate_distance()));
+    (count > 0).then(|| total / count as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::test_trip;
+
+    fn on(year: i32, month: u32, day: u32) -> Trip {
+        Trip {
+            date: NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+            ..test_trip()
+        }
+    }
+
+    #[test]
+    fn months_add_up_their_trips_and_returns_count_twice() {
+        let trips = [
+            on(2024, 1, 10),
+            Trip {
+                returning: true,
+                ..on(2024, 1, 20)
+            },
+            on(2024, 12, 31),
+            on(2023, 1, 10),
+        ];
+        let months = per_month(&trips, 2024);
+        // 3,2 km and 7 minutes each way.
+        assert!((months[0].0 - 9.6).abs() < 1e-4);
+        assert!((months[0].1 - 0.35).abs() < 1e-4);
+        assert!((months[11].0 - 3.2).abs() < 1e-4);
+        assert!(months[1..11].iter().all(|&m| m == (0., 0.)));
+    }
+
+    #[test]
+    fn a_year_without_trips_is_all_zeros() {
+        let trips = [on(2023, 6, 1)];
+        assert_eq!(per_month(&trips, 2024), [(0., 0.); 12]);
+        assert_eq!(per_weekday([]), [0; 7]);
+        assert_eq!(average_distance([]), None);
+        assert!(top_reasons([], 5).is_empty());
+    }
+
+    #[test]
+    fn reasons_are_grouped_after_trimming_and_ties_go_alphabetically() {
+        let reason = |reason: &str| Trip {
+            reason: reason.into(),
+            ..test_trip()
+        };
+        let trips = [
+            reason("Möte"),
+            reason(" Möte "),
+            reason("Utbildning"),
+            reason("Besök"),
+            reason("Möte"),
+        ];
+        assert_eq!(
+            top_reasons(&trips, 2),
+            [("Möte".to_owned(), 3), ("Besök".to_owned(), 1)]
+        );
+    }
+
+    #[test]
+    fn weekdays_start_on_monday() {
+        // 2024-03-04 was a Monday and 2024-03-10 a Sunday.
+        let trips = [on(2024, 3, 4), on(2024, 3, 10), on(2024, 3, 11)];
+        assert_eq!(per_weekday(&trips), [2, 0, 0, 0, 0, 0, 1]);
+    }
+}
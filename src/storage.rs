@@ -0,0 +1,324 @@
+use std::{cell::Cell, fmt::Display};
+
+use leptos::*;
+use leptos_use::{
+    storage::{use_local_storage_with_options, UseStorageError, UseStorageOptions},
+    utils::StringCodec,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::Trips;
+
+pub const TRIPS_KEY: &str = "my-trips";
+/// Where an unreadable `my-trips` payload is copied before anything else can touch it.
+pub const QUARANTINE_KEY: &str = "my-trips-unreadable";
+/// Bump this and append a step to [`MIGRATIONS`] whenever the shape of `Trips` or `Trip` changes.
+pub const SCHEMA_VERSION: u64 = 7;
+
+type Migration = fn(Map<String, Value>) -> Result<Map<String, Value>, SchemaError>;
+
+/// `MIGRATIONS[n]` upgrades a document from version `n` to `n + 1`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+    v0_to_v1, v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7,
+];
+
+thread_local! {
+    /// Set while the stored document could not be read, so that we never replace it with defaults.
+    static LOCKED: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// The payload is not valid JSON or does not have the expected shape.
+    Unreadable(String),
+    /// The payload was written by a newer version of the app.
+    FromTheFuture(u64),
+    /// Writing is refused because the stored payload could not be read.
+    Locked,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreadable(reason) => write!(f, "Resorna kunde inte läsas: {reason}"),
+            Self::FromTheFuture(version) => write!(
+                f,
+                "Resorna är sparade med en nyare version ({version}) av appen"
+            ),
+            Self::Locked => write!(f, "Resorna skrivs inte över eftersom de inte kunde läsas"),
+        }
+    }
+}
+
+/// The envelope actually written to storage.
+#[derive(Serialize)]
+struct Stored<'a> {
+    version: u64,
+    #[serde(flatten)]
+    trips: &'a Trips,
+}
+
+/// Reads a stored document of any known version, upgrading it step by step.
+pub fn load(raw: &str) -> Result<Trips, SchemaError> {
+    let value: Value =
+        serde_json::from_str(raw).map_err(|e| SchemaError::Unreadable(e.to_string()))?;
+    let Value::Object(mut doc) = value else {
+        return Err(SchemaError::Unreadable("inget objekt".into()));
+    };
+    // Documents from before versioning have no version field at all.
+    let version = match doc.remove("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .ok_or_else(|| SchemaError::Unreadable(format!("ogiltig version {v}")))?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(SchemaError::FromTheFuture(version));
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        doc = step(doc)?;
+    }
+    serde_json::from_value(Value::Object(doc)).map_err(|e| SchemaError::Unreadable(e.to_string()))
+}
+
+pub fn save(trips: &Trips) -> Result<String, SchemaError> {
+    let stored = Stored {
+        version: SCHEMA_VERSION,
+        trips,
+    };
+    serde_json::to_string(&stored).map_err(|e| SchemaError::Unreadable(e.to_string()))
+}
+
+/// Applies `change` to every trip in the document.
+fn each_trip(
+    mut doc: Map<String, Value>,
+    change: impl Fn(&mut Map<String, Value>),
+) -> Result<Map<String, Value>, SchemaError> {
+    let trips = doc
+        .entry("trips")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(|| SchemaError::Unreadable("trips är ingen lista".into()))?;
+    for trip in trips {
+        let trip = trip
+            .as_object_mut()
+            .ok_or_else(|| SchemaError::Unreadable("resa är inget objekt".into()))?;
+        change(trip);
+    }
+    Ok(doc)
+}
+
+/// Trips used to lack `uuid` and `returning`, and relied on serde defaults to fill them in.
+fn v0_to_v1(doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    each_trip(doc, |trip| {
+        trip.entry("uuid")
+            .or_insert_with(|| Value::String(Uuid::new_v4().to_string()));
+        trip.entry("returning").or_insert(Value::Bool(false));
+    })
+}
+
+/// Odometer readings were added as an optional part of each trip.
+fn v1_to_v2(doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    each_trip(doc, |trip| {
+        trip.entry("odometer").or_insert(Value::Null);
+    })
+}
+
+/// A registry of vehicles was added next to the trips, and each trip may name one.
+fn v2_to_v3(mut doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    doc.entry("vehicles")
+        .or_insert_with(|| json!({ "vehicles": [], "last_used": null }));
+    each_trip(doc, |trip| {
+        trip.entry("vehicle").or_insert(Value::Null);
+    })
+}
+
+/// Trips can be edited in place, which is recorded with a timestamp.
+fn v3_to_v4(doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    each_trip(doc, |trip| {
+        trip.entry("edited").or_insert(Value::Null);
+    })
+}
+
+/// Trips can pass through stops on the way to their destination.
+fn v4_to_v5(doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    each_trip(doc, |trip| {
+        trip.entry("stops")
+            .or_insert_with(|| Value::Array(Vec::new()));
+    })
+}
+
+/// Trips are split into categories, and everything logged so far was a tjänsteresa.
+fn v5_to_v6(doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    each_trip(doc, |trip| {
+        trip.entry("category")
+            .or_insert_with(|| Value::String("Business".into()));
+    })
+}
+
+/// A registry of cost centres was added next to the trips, and each trip may be billed to one.
+fn v6_to_v7(mut doc: Map<String, Value>) -> Result<Map<String, Value>, SchemaError> {
+    doc.entry("cost_centres")
+        .or_insert_with(|| json!({ "centres": [] }));
+    each_trip(doc, |trip| {
+        trip.entry("cost_centre").or_insert(Value::Null);
+    })
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TripsCodec;
+
+impl StringCodec<Trips> for TripsCodec {
+    type Error = SchemaError;
+
+    fn encode(&self, val: &Trips) -> Result<String, Self::Error> {
+        if LOCKED.get() {
+            return Err(SchemaError::Locked);
+        }
+        save(val)
+    }
+
+    fn decode(&self, str: String) -> Result<Trips, Self::Error> {
+        let loaded = load(&str);
+        LOCKED.set(loaded.is_err());
+        if loaded.is_err() {
+            quarantine(&str);
+        }
+        loaded
+    }
+}
+
+/// Keeps a copy of a payload we could not read, in case a later write gets through anyway.
+fn quarantine(raw: &str) {
+    if let Ok(Some(storage)) = window().local_storage() {
+        _ = storage.set_item(QUARANTINE_KEY, raw);
+    }
+}
+
+/// Shows why the trips could not be read, provided by `App`.
+#[derive(Debug, Clone, Copy)]
+pub struct StorageProblem(pub RwSignal<Option<String>>);
+
+pub fn use_trips() -> (Signal<Trips>, WriteSignal<Trips>) {
+    let problem = use_context::<StorageProblem>();
+    let options = UseStorageOptions::<Trips, TripsCodec>::default().on_error(move |e| {
+        let message = match e {
+            UseStorageError::ItemCodecError(e) => e.to_string(),
+            e => e.to_string(),
+        };
+        logging::error!("{message}");
+        if let Some(StorageProblem(problem)) = problem {
+            problem.set(Some(message));
+        }
+    });
+    let (r_trips, w_trips, _) = use_local_storage_with_options(TRIPS_KEY, options);
+    (r_trips, w_trips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::Category;
+
+    #[test]
+    fn reads_v0_without_uuid_or_returning() {
+        let raw = r#"{"trips":[{"date":"2024-01-15","from":"Kommunhuset","to":"Ekenässkolan","distance":3.2,"time":7,"reason":"Möte"}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(trips.trips.len(), 1);
+        assert!(!trips.trips[0].returning);
+        assert!(!trips.trips[0].uuid.is_nil());
+    }
+
+    #[test]
+    fn reads_v0_with_uuid_and_returning() {
+        let raw = r#"{"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-02-01","from":"Kommunhuset","to":"Ekenässkolan","distance":3.2,"time":7,"reason":"Möte","returning":true}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(
+            trips.trips[0].uuid,
+            Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap()
+        );
+        assert!(trips.trips[0].returning);
+    }
+
+    #[test]
+    fn reads_v1_without_odometer() {
+        let raw = r#"{"version":1,"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-03-01","from":"A","to":"B","distance":4.0,"time":6,"reason":"Möte","returning":false}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(trips.trips[0].odometer, None);
+        assert_eq!(trips.trips[0].calculate_distance(), 4.0);
+    }
+
+    #[test]
+    fn reads_v2_without_vehicles() {
+        let raw = r#"{"version":2,"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-03-01","from":"A","to":"B","distance":4.0,"time":6,"reason":"Möte","returning":false,"odometer":{"start":100,"end":104}}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(trips.trips[0].vehicle, None);
+        assert!(trips.vehicles.all().is_empty());
+        assert_eq!(trips.trips[0].calculate_distance(), 4.0);
+    }
+
+    #[test]
+    fn reads_v3_without_edit_timestamp() {
+        let raw = r#"{"version":3,"vehicles":{"vehicles":[],"last_used":null},"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-03-01","from":"A","to":"B","distance":4.0,"time":6,"reason":"Möte","returning":false,"odometer":null,"vehicle":null}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(trips.trips[0].edited, None);
+    }
+
+    #[test]
+    fn reads_v4_without_stops() {
+        let raw = r#"{"version":4,"vehicles":{"vehicles":[],"last_used":null},"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-03-01","from":"A","to":"B","distance":4.0,"time":6,"reason":"Möte","returning":true,"odometer":null,"vehicle":null,"edited":null}]}"#;
+        let trips = load(raw).unwrap();
+        assert!(trips.trips[0].stops.is_empty());
+        assert_eq!(trips.trips[0].from_to(), "A-B ToR");
+    }
+
+    #[test]
+    fn reads_v5_without_category() {
+        let raw = r#"{"version":5,"vehicles":{"vehicles":[],"last_used":null},"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-03-01","from":"A","stops":[],"to":"B","distance":4.0,"time":6,"reason":"Möte","returning":false,"odometer":null,"vehicle":null,"edited":null}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(trips.trips[0].category, Category::Business);
+    }
+
+    #[test]
+    fn reads_v6_without_cost_centres() {
+        let raw = r#"{"version":6,"vehicles":{"vehicles":[],"last_used":null},"trips":[{"uuid":"67e55044-10b1-426f-9247-bb680e5fe0c8","date":"2024-03-01","from":"A","stops":[],"to":"B","distance":4.0,"time":6,"reason":"Möte","category":"Private","returning":false,"odometer":null,"vehicle":null,"edited":null}]}"#;
+        let trips = load(raw).unwrap();
+        assert_eq!(trips.trips[0].cost_centre, None);
+        assert_eq!(trips.trips[0].category, Category::Private);
+        assert!(trips.cost_centres.all().is_empty());
+    }
+
+    #[test]
+    fn reads_empty_v0() {
+        assert_eq!(load("{}").unwrap(), Trips::default());
+    }
+
+    #[test]
+    fn roundtrips_current_version() {
+        let raw = r#"{"trips":[{"date":"2024-01-15","from":"A","to":"B","distance":1.5,"time":3,"reason":"Möte"}]}"#;
+        let trips = load(raw).unwrap();
+        let saved = save(&trips).unwrap();
+        assert!(saved.contains(&format!("\"version\":{SCHEMA_VERSION}")));
+        assert_eq!(load(&saved).unwrap(), trips);
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let raw = format!(r#"{{"version":{},"trips":[]}}"#, SCHEMA_VERSION + 1);
+        assert_eq!(
+            load(&raw),
+            Err(SchemaError::FromTheFuture(SCHEMA_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn refuses_garbage() {
+        assert!(matches!(load("[1, 2]"), Err(SchemaError::Unreadable(_))));
+        assert!(matches!(
+            load(r#"{"trips":[{"date":"igår"}]}"#),
+            Err(SchemaError::Unreadable(_))
+        ));
+    }
+}
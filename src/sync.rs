@@ -0,0 +1,498 @@
+//! Keeps trips in step across devices through the optional server in `src/bin/sync-server.rs`.
+//!
+//! The vehicles and cost centres trips refer to travel along as records of their own, told apart
+//! by a `kind` field that trip records, which came first, do without. Every field of a record
+//! carries its own stamp, so edits to different fields on different devices both survive, and the
+//! last write wins when the same field was changed twice. Deleting is just another field,
+//! `deleted`, which lets a trip restored from the trash come back everywhere.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{Local, NaiveDateTime, Utc};
+use leptos::*;
+use leptos_use::{storage::use_local_storage, utils::JsonCodec};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::{cost_centres::CostCentre, vehicles::Vehicle, Trip, Trips};
+
+pub const DELETED: &str = "deleted";
+const KIND: &str = "kind";
+const VEHICLE: &str = "vehicle";
+const COST_CENTRE: &str = "cost_centre";
+
+/// Cost centres are known by their code alone, so their records are keyed by a name-based uuid.
+fn cost_centre_key(code: &str) -> Uuid {
+    Uuid::new_v5(
+        &Uuid::NAMESPACE_OID,
+        format!("servis.cost_centre.{code}").as_bytes(),
+    )
+}
+
+/// Every synced thing in `trips` as the key of its record and its fields, without the key itself.
+fn entries(trips: &Trips) -> Vec<(Uuid, Map<String, Value>)> {
+    let fields = |value: serde_json::Result<Value>| match value {
+        Ok(Value::Object(fields)) => Some(fields),
+        _ => None,
+    };
+    let kind = |mut fields: Map<String, Value>, kind: &str| {
+        fields.insert(KIND.to_owned(), Value::String(kind.to_owned()));
+        fields
+    };
+    let trip_entries = trips.trips.iter().filter_map(|trip| {
+        let mut fields = fields(serde_json::to_value(trip))?;
+        fields.remove("uuid");
+        Some((trip.uuid, fields))
+    });
+    let vehicle_entries = trips.vehicles.all().iter().filter_map(|vehicle| {
+        let mut fields = fields(serde_json::to_value(vehicle))?;
+        fields.remove("id");
+        Some((vehicle.id, kind(fields, VEHICLE)))
+    });
+    let cost_centre_entries = trips.cost_centres.all().iter().filter_map(|centre| {
+        let fields = fields(serde_json::to_value(centre))?;
+        Some((cost_centre_key(&centre.code), kind(fields, COST_CENTRE)))
+    });
+    trip_entries
+        .chain(vehicle_entries)
+        .chain(cost_centre_entries)
+        .collect()
+}
+
+/// When and where a field was written. Ordered by time, with the device breaking ties.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Stamp {
+    pub at: i64,
+    pub device: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Field {
+    pub value: Value,
+    pub stamp: Stamp,
+}
+
+/// A trip, vehicle or cost centre as a set of separately stamped fields, or only the changed ones
+/// when sent.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    pub uuid: Uuid,
+    pub fields: BTreeMap<String, Field>,
+}
+
+impl Record {
+    pub fn new(uuid: Uuid) -> Self {
+        Self {
+            uuid,
+            fields: BTreeMap::new(),
+        }
+    }
+    /// Keeps whichever side wrote each field last.
+    pub fn merge(&mut self, other: Record) {
+        for (name, field) in other.fields {
+            match self.fields.get(&name) {
+                Some(current) if current.stamp >= field.stamp => {}
+                _ => {
+                    self.fields.insert(name, field);
+                }
+            }
+        }
+    }
+    pub fn is_deleted(&self) -> bool {
+        self.fields
+            .get(DELETED)
+            .is_some_and(|f| f.value == Value::Bool(true))
+    }
+    fn kind(&self) -> Option<&str> {
+        self.fields.get(KIND).and_then(|f| f.value.as_str())
+    }
+    /// The fields as a `T`, with the uuid going back into `key` for the things known by it.
+    fn decode<T: DeserializeOwned>(&self, key: Option<&str>) -> serde_json::Result<T> {
+        let mut fields: Map<String, Value> = self
+            .fields
+            .iter()
+            .filter(|(name, _)| *name != DELETED && *name != KIND)
+            .map(|(name, f)| (name.clone(), f.value.clone()))
+            .collect();
+        if let Some(key) = key {
+            fields.insert(key.into(), Value::String(self.uuid.to_string()));
+        }
+        serde_json::from_value(Value::Object(fields))
+    }
+}
+
+/// What a client sends: its own changes, and how far it has read the server's.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Push {
+    pub since: u64,
+    pub changes: Vec<Record>,
+}
+
+/// What the server answers: every field changed after `since`, and where to continue next time.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Pull {
+    pub cursor: u64,
+    pub changes: Vec<Record>,
+}
+
+/// This device's view of the synced records, kept next to the trips themselves.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct SyncState {
+    device: Uuid,
+    cursor: u64,
+    records: HashMap<Uuid, Record>,
+    pub last_synced: Option<NaiveDateTime>,
+}
+
+impl Default for SyncState {
+    fn default() -> Self {
+        Self {
+            device: Uuid::new_v4(),
+            cursor: 0,
+            records: HashMap::new(),
+            last_synced: None,
+        }
+    }
+}
+
+impl SyncState {
+    /// Stamps whatever differs between `trips` and the last sync, returning just those fields.
+    fn record_local(&mut self, trips: &Trips, at: i64) -> Vec<Record> {
+        let stamp = Stamp {
+            at,
+            device: self.device,
+        };
+        let mut changes = Vec::new();
+        let set = |record: &mut Record, name: &str, value: Value| {
+            if record.fields.get(name).map(|f| &f.value) != Some(&value) {
+                let field = Field { value, stamp };
+                record.fields.insert(name.to_owned(), field.clone());
+                Some((name.to_owned(), field))
+            } else {
+                None
+            }
+        };
+        // What was never handed to the app cannot have been deleted in it.
+        let unreadable = self.unreadable();
+        let entries = entries(trips);
+        let mut present = Vec::with_capacity(entries.len());
+        for (uuid, mut fields) in entries {
+            present.push(uuid);
+            fields.insert(DELETED.to_owned(), Value::Bool(false));
+            let record = self
+                .records
+                .entry(uuid)
+                .or_insert_with(|| Record::new(uuid));
+            let mut changed = Record::new(uuid);
+            for (name, value) in fields {
+                if let Some((name, field)) = set(record, &name, value) {
+                    changed.fields.insert(name, field);
+                }
+            }
+            if !changed.fields.is_empty() {
+                changes.push(changed);
+            }
+        }
+        for record in self.records.values_mut() {
+            if present.contains(&record.uuid) || unreadable.contains(&record.uuid) {
+                continue;
+            }
+            if let Some((name, field)) = set(record, DELETED, Value::Bool(true)) {
+                let mut changed = Record::new(record.uuid);
+                changed.fields.insert(name, field);
+                changes.push(changed);
+            }
+        }
+        changes
+    }
+
+    fn apply(&mut self, pull: Pull) {
+        for incoming in pull.changes {
+            self.records
+                .entry(incoming.uuid)
+                .or_insert_with(|| Record::new(incoming.uuid))
+                .merge(incoming);
+        }
+        self.cursor = self.cursor.max(pull.cursor);
+    }
+
+    /// The records of one `kind` as they stand after merging, leaving out deleted ones and those
+    /// that no longer fit the type, such as trips from a newer version.
+    fn read<T: DeserializeOwned>(&self, kind: Option<&str>, key: Option<&str>) -> Vec<T> {
+        self.records
+            .values()
+            .filter(|r| !r.is_deleted() && r.kind() == kind)
+            .filter_map(|record| {
+                record
+                    .decode(key)
+                    .map_err(|e| logging::warn!("Hoppar över post {}: {e}", record.uuid))
+                    .ok()
+            })
+            .collect()
+    }
+    /// The live records `read` skips, so `restore` never handed them out.
+    fn unreadable(&self) -> HashSet<Uuid> {
+        self.records
+            .values()
+            .filter(|r| !r.is_deleted())
+            .filter(|r| match r.kind() {
+                None => r.decode::<Trip>(Some("uuid")).is_err(),
+                Some(VEHICLE) => r.decode::<Vehicle>(Some("id")).is_err(),
+                Some(COST_CENTRE) => r.decode::<CostCentre>(None).is_err(),
+                Some(_) => true,
+            })
+            .map(|r| r.uuid)
+            .collect()
+    }
+    pub fn trips(&self) -> Vec<Trip> {
+        self.read(None, Some("uuid"))
+    }
+    pub fn vehicles(&self) -> Vec<Vehicle> {
+        self.read(Some(VEHICLE), Some("id"))
+    }
+    pub fn cost_centres(&self) -> Vec<CostCentre> {
+        self.read(Some(COST_CENTRE), None)
+    }
+    /// Puts the merged trips and registries in place of the ones in `trips`.
+    pub fn restore(&self, trips: &mut Trips) {
+        trips.replace(self.trips());
+        trips.vehicles.replace(self.vehicles());
+        trips.cost_centres.replace(self.cost_centres());
+    }
+
+    /// Pushes local changes and pulls everyone else's, returning the state to keep on success.
+    pub async fn sync(mut self, url: &str, token: &str, trips: &Trips) -> Result<Self, String> {
+        let now = Utc::now();
+        let push = Push {
+            since: self.cursor,
+            changes: self.record_local(trips, now.timestamp_millis()),
+        };
+        let mut request =
+            gloo::net::http::Request::post(&format!("{}/sync", url.trim_end_matches('/')));
+        if !token.is_empty() {
+            request = request.header("Authorization", &format!("Bearer {token}"));
+        }
+        let response = request
+            .json(&push)
+            .map_err(|e| e.to_string())?
+            .send()
+            .await
+            .map_err(|e| format!("Servern svarar inte: {e}"))?;
+        if !response.ok() {
+            return Err(format!("Servern svarade {}", response.status()));
+        }
+        let pull: Pull = response.json().await.map_err(|e| e.to_string())?;
+        self.apply(pull);
+        // Stamps are compared in UTC, but the time is shown to the user as it reads locally.
+        self.last_synced = Some(Local::now().naive_local());
+        Ok(self)
+    }
+}
+
+pub(crate) fn use_sync_state() -> (Signal<SyncState>, WriteSignal<SyncState>) {
+    let (r_state, w_state, _) = use_local_storage::<SyncState, JsonCodec>("my-sync");
+    (r_state, w_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        cost_centres::CostCentre,
+        test_trip,
+        vehicles::{Ownership, Vehicle},
+    };
+
+    fn stamp(at: i64, device: u128) -> Stamp {
+        Stamp {
+            at,
+            device: Uuid::from_u128(device),
+        }
+    }
+
+    fn record(uuid: Uuid, fields: &[(&str, Value, Stamp)]) -> Record {
+        let mut record = Record::new(uuid);
+        for (name, value, stamp) in fields {
+            let field = Field {
+                value: value.clone(),
+                stamp: *stamp,
+            };
+            record.fields.insert(name.to_string(), field);
+        }
+        record
+    }
+
+    fn trip(reason: &str) -> Trip {
+        Trip {
+            uuid: Uuid::from_u128(7),
+            reason: reason.into(),
+            ..test_trip()
+        }
+    }
+
+    fn only(trip: Trip) -> Trips {
+        Trips {
+            trips: vec![trip],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_keeps_the_last_write_of_each_field() {
+        let uuid = Uuid::from_u128(1);
+        let mut ours = record(
+            uuid,
+            &[
+                ("reason", json!("Möte"), stamp(10, 1)),
+                ("time", json!(7), stamp(30, 1)),
+            ],
+        );
+        ours.merge(record(
+            uuid,
+            &[
+                ("reason", json!("Kurs"), stamp(20, 2)),
+                ("time", json!(9), stamp(20, 2)),
+            ],
+        ));
+        assert_eq!(ours.fields["reason"].value, json!("Kurs"));
+        assert_eq!(ours.fields["time"].value, json!(7));
+    }
+
+    #[test]
+    fn merge_breaks_ties_by_device() {
+        let uuid = Uuid::from_u128(1);
+        let mut ours = record(uuid, &[("reason", json!("Möte"), stamp(10, 1))]);
+        ours.merge(record(uuid, &[("reason", json!("Kurs"), stamp(10, 2))]));
+        assert_eq!(ours.fields["reason"].value, json!("Kurs"));
+        // The very same stamp changes nothing.
+        ours.merge(record(uuid, &[("reason", json!("Annat"), stamp(10, 2))]));
+        assert_eq!(ours.fields["reason"].value, json!("Kurs"));
+    }
+
+    #[test]
+    fn merge_lets_the_later_of_deleting_and_restoring_win() {
+        let uuid = Uuid::from_u128(1);
+        let mut ours = record(uuid, &[(DELETED, json!(false), stamp(10, 1))]);
+        ours.merge(record(uuid, &[(DELETED, json!(true), stamp(20, 2))]));
+        assert!(ours.is_deleted());
+        ours.merge(record(uuid, &[(DELETED, json!(false), stamp(15, 1))]));
+        assert!(ours.is_deleted());
+        ours.merge(record(uuid, &[(DELETED, json!(false), stamp(30, 1))]));
+        assert!(!ours.is_deleted());
+    }
+
+    #[test]
+    fn record_local_stamps_only_changed_fields() {
+        let mut state = SyncState::default();
+        let first = state.record_local(&only(trip("Möte")), 10);
+        assert_eq!(first.len(), 1);
+        assert!(first[0].fields.contains_key("reason"));
+        assert!(first[0].fields.contains_key(DELETED));
+        assert!(!first[0].fields.contains_key("uuid"));
+
+        assert!(state.record_local(&only(trip("Möte")), 20).is_empty());
+
+        let second = state.record_local(&only(trip("Kurs")), 30);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].fields.keys().collect_vec(), ["reason"]);
+        assert_eq!(second[0].fields["reason"].stamp.at, 30);
+    }
+
+    #[test]
+    fn record_local_leaves_a_tombstone_for_removed_trips() {
+        let mut state = SyncState::default();
+        state.record_local(&only(trip("Möte")), 10);
+        let changes = state.record_local(&Trips::default(), 20);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].fields.keys().collect_vec(), [DELETED]);
+        assert!(changes[0].is_deleted());
+        assert!(state.trips().is_empty());
+    }
+
+    #[test]
+    fn unreadable_records_are_never_tombstoned() {
+        let (uuid, fields) = entries(&only(test_trip())).remove(0);
+        // A trip from a newer version, or a damaged one, without a date.
+        let mut damaged = Record::new(uuid);
+        for (name, value) in fields.into_iter().filter(|(name, _)| name != "date") {
+            let stamp = stamp(5, 2);
+            damaged.fields.insert(name, Field { value, stamp });
+        }
+        let mut state = SyncState::default();
+        state.apply(Pull {
+            cursor: 1,
+            changes: vec![damaged],
+        });
+
+        let mut trips = Trips::default();
+        for at in [10, 20] {
+            state.restore(&mut trips);
+            assert!(trips.trips.is_empty());
+            let changes = state.record_local(&trips, at);
+            assert!(changes.iter().all(|c| !c.fields.contains_key(DELETED)));
+        }
+        assert!(!state.records[&uuid].fields.contains_key(DELETED));
+    }
+
+    #[test]
+    fn applied_changes_come_back_as_the_same_trip() {
+        let mut ours = SyncState::default();
+        let changes = ours.record_local(&only(trip("Möte")), 10);
+        let mut theirs = SyncState::default();
+        theirs.apply(Pull { cursor: 5, changes });
+        assert_eq!(theirs.trips(), vec![trip("Möte")]);
+        assert_eq!(theirs.cursor, 5);
+    }
+
+    #[test]
+    fn a_synced_trip_finds_its_vehicle_and_cost_centre() {
+        let car = Vehicle {
+            id: Uuid::from_u128(3),
+            registration: "ABC123".into(),
+            ownership: Ownership::Own,
+            ..Default::default()
+        };
+        let mut ours = only(Trip {
+            vehicle: Some(car.id),
+            cost_centre: Some("4100".into()),
+            ..trip("Möte")
+        });
+        ours.vehicles.add(car.clone());
+        ours.cost_centres.add(CostCentre {
+            code: "4100".into(),
+            name: "Grundskola".into(),
+        });
+        let changes = SyncState::default().record_local(&ours, 10);
+
+        let mut theirs = SyncState::default();
+        theirs.apply(Pull { cursor: 1, changes });
+        let mut received = Trips::default();
+        theirs.restore(&mut received);
+        let trip = &received.trips[0];
+        assert_eq!(received.vehicles.all(), [car]);
+        assert!(received.vehicles.reimbursable(trip));
+        assert_eq!(received.cost_centres.name_of("4100"), "4100 Grundskola");
+        assert_eq!(received.trips, ours.trips);
+    }
+
+    #[test]
+    fn removed_vehicles_leave_a_tombstone_too() {
+        let mut ours = Trips::default();
+        ours.vehicles.add(Vehicle {
+            id: Uuid::from_u128(3),
+            registration: "ABC123".into(),
+            ..Default::default()
+        });
+        let mut state = SyncState::default();
+        state.record_local(&ours, 10);
+        ours.vehicles.remove(&Uuid::from_u128(3));
+        let changes = state.record_local(&ours, 20);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].is_deleted());
+        assert!(state.vehicles().is_empty());
+    }
+}
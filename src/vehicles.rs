@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Trip;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Ownership {
+    #[default]
+    Own,
+    Pool,
+    Leased,
+}
+
+impl Ownership {
+    pub const ALL: [Ownership; 3] = [Self::Own, Self::Pool, Self::Leased];
+
+    pub fn for_humans(&self) -> &'static str {
+        match self {
+            Self::Own => "Egen bil",
+            Self::Pool => "Poolbil",
+            Self::Leased => "Leasad",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Fuel {
+    #[default]
+    Petrol,
+    Diesel,
+    Electric,
+    Hybrid,
+    Gas,
+}
+
+impl Fuel {
+    pub const ALL: [Fuel; 5] = [
+        Self::Petrol,
+        Self::Diesel,
+        Self::Electric,
+        Self::Hybrid,
+        Self::Gas,
+    ];
+
+    pub fn for_humans(&self) -> &'static str {
+        match self {
+            Self::Petrol => "Bensin",
+            Self::Diesel => "Diesel",
+            Self::Electric => "El",
+            Self::Hybrid => "Laddhybrid",
+            Self::Gas => "Gas",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Vehicle {
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    pub registration: String,
+    pub name: String,
+    pub ownership: Ownership,
+    pub fuel: Fuel,
+}
+
+impl Vehicle {
+    pub fn for_humans(&self) -> String {
+        if self.name.is_empty() {
+            self.registration.clone()
+        } else {
+            format!("{} ({})", self.name, self.registration)
+        }
+    }
+}
+
+/// The cars trips can be logged on, kept in the same document as the trips.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Vehicles {
+    vehicles: Vec<Vehicle>,
+    /// Preselected when logging the next trip.
+    last_used: Option<Uuid>,
+}
+
+impl Vehicles {
+    pub fn all(&self) -> &[Vehicle] {
+        &self.vehicles
+    }
+    pub fn get(&self, id: &Uuid) -> Option<&Vehicle> {
+        self.vehicles.iter().find(|v| v.id == *id)
+    }
+    pub fn by_registration(&self, registration: &str) -> Option<&Vehicle> {
+        self.vehicles
+            .iter()
+            .find(|v| v.registration.eq_ignore_ascii_case(registration))
+    }
+    pub fn add(&mut self, vehicle: Vehicle) {
+        self.vehicles.push(vehicle);
+    }
+    /// Adds the vehicles from `other` that are not already known by id.
+    pub fn merge(&mut self, other: Vehicles) {
+        for vehicle in other.vehicles {
+            if self.get(&vehicle.id).is_none() {
+                self.add(vehicle);
+            }
+        }
+        if self.last_used.is_none() {
+            self.last_used = other.last_used;
+        }
+    }
+    /// Swaps in the vehicles from a sync, keeping the ones already known in their order.
+    pub fn replace(&mut self, mut vehicles: Vec<Vehicle>) {
+        vehicles.sort_by_cached_key(|v| {
+            let known = self.vehicles.iter().position(|k| k.id == v.id);
+            (known.unwrap_or(usize::MAX), v.registration.clone())
+        });
+        self.vehicles = vehicles;
+    }
+    pub fn remove(&mut self, id: &Uuid) {
+        self.vehicles.retain(|v| v.id != *id);
+        if self.last_used == Some(*id) {
+            self.last_used = None;
+        }
+    }
+    pub fn last_used(&self) -> Option<Uuid> {
+        self.last_used.filter(|id| self.get(id).is_some())
+    }
+    pub fn use_vehicle(&mut self, id: Option<Uuid>) {
+        if id.is_some() {
+            self.last_used = id;
+        }
+    }
+    pub fn name_of(&self, trip: &Trip) -> String {
+        trip.vehicle
+            .and_then(|id| self.get(&id))
+            .map(Vehicle::for_humans)
+            .unwrap_or_else(|| "Inget fordon".to_owned())
+    }
+    /// Distance per vehicle, in the order the vehicles were added.
+    pub fn totals<'a>(&self, trips: impl IntoIterator<Item = &'a Trip>) -> Vec<(String, f32)> {
+        let mut totals: Vec<(Option<Uuid>, f32)> = Vec::new();
+        for trip in trips {
+            match totals.iter_mut().find(|(id, _)| *id == trip.vehicle) {
+                Some((_, distance)) => *distance += trip.calculate_distance(),
+                None => totals.push((trip.vehicle, trip.calculate_distance())),
+            }
+        }
+        totals.sort_by_key(|(id, _)| {
+            id.and_then(|id| self.vehicles.iter().position(|v| v.id == id))
+                .unwrap_or(usize::MAX)
+        });
+        totals
+            .into_iter()
+            .map(|(id, distance)| {
+                let name = id
+                    .and_then(|id| self.get(&id))
+                    .map(Vehicle::for_humans)
+                    .unwrap_or_else(|| "Inget fordon".to_owned());
+                (name, distance)
+            })
+            .collect()
+    }
+    /// Only tjänsteresor give milersättning, and not in pool cars which the employer pays for.
+    /// A vehicle that is not in the registry could be a pool car, so it gives none either.
+    pub fn reimbursable(&self, trip: &Trip) -> bool {
+        let by_own_car = match trip.vehicle {
+            None => true,
+            Some(id) => self
+                .get(&id)
+                .is_some_and(|v| v.ownership != Ownership::Pool),
+        };
+        trip.category.is_business() && by_own_car
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{category::Category, test_trip};
+
+    fn vehicles() -> (Vehicles, Uuid, Uuid) {
+        let own = Vehicle {
+            id: Uuid::new_v4(),
+            registration: "ABC123".into(),
+            name: "Golfen".into(),
+            ..Default::default()
+        };
+        let pool = Vehicle {
+            id: Uuid::new_v4(),
+            registration: "XYZ789".into(),
+            ownership: Ownership::Pool,
+            ..Default::default()
+        };
+        let (own_id, pool_id) = (own.id, pool.id);
+        let mut vehicles = Vehicles::default();
+        vehicles.add(own);
+        vehicles.add(pool);
+        (vehicles, own_id, pool_id)
+    }
+
+    #[test]
+    fn only_business_trips_outside_pool_cars_are_reimbursable() {
+        let (vehicles, own, pool) = vehicles();
+        let trip = |vehicle, category| Trip {
+            vehicle,
+            category,
+            ..test_trip()
+        };
+        assert!(vehicles.reimbursable(&trip(Some(own), Category::Business)));
+        assert!(vehicles.reimbursable(&trip(None, Category::Business)));
+        assert!(!vehicles.reimbursable(&trip(Some(pool), Category::Business)));
+        assert!(!vehicles.reimbursable(&trip(Some(own), Category::Private)));
+        assert!(!vehicles.reimbursable(&trip(Some(own), Category::Commute)));
+        let unknown = trip(Some(Uuid::new_v4()), Category::Business);
+        assert!(!vehicles.reimbursable(&unknown));
+    }
+
+    #[test]
+    fn totals_follow_the_order_vehicles_were_added() {
+        let (vehicles, own, pool) = vehicles();
+        let trip = |vehicle, returning| Trip {
+            vehicle,
+            returning,
+            ..test_trip()
+        };
+        let trips = [
+            trip(None, false),
+            trip(Some(pool), false),
+            trip(Some(own), true),
+            trip(Some(own), false),
+        ];
+        let totals: Vec<_> = vehicles
+            .totals(&trips)
+            .into_iter()
+            .map(|(name, km)| (name, (km * 10.).round() / 10.))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                ("Golfen (ABC123)".to_owned(), 9.6),
+                ("XYZ789".to_owned(), 3.2),
+                ("Inget fordon".to_owned(), 3.2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,85 @@
+// Keeps the app working offline. The version is stamped in by the post_build hook in Trunk.toml,
+// so every deployment installs a new worker that the app can offer to switch to.
+const VERSION = "__BUILD__";
+const PREFIX = "loggbok-";
+const CACHE = PREFIX + VERSION;
+const SCOPE = self.registration.scope;
+const ICONS = [
+  "public/manifest.json",
+  "public/favicon.ico",
+  "public/icons/icon-72.png",
+  "public/icons/icon-96.png",
+  "public/icons/icon-152.png",
+  "public/icons/icon-192.png",
+  "public/icons/icon-384.png",
+  "public/icons/icon-512.png",
+  "public/icons/apple-icon-180.png",
+];
+
+// Trunk gives the wasm, js and css hashed names, so they are read from the fresh index.html.
+async function precache() {
+  const cache = await caches.open(CACHE);
+  const shell = await fetch(SCOPE, { cache: "no-cache" });
+  if (!shell.ok) {
+    throw new Error(`index.html: ${shell.status}`);
+  }
+  const html = await shell.clone().text();
+  const bundle = [...html.matchAll(/(?:href|src)="([^"]+)"/g)]
+    .map((match) => new URL(match[1], SCOPE).href)
+    .filter((url) => url.startsWith(SCOPE));
+  const assets = new Set([...bundle, ...ICONS.map((icon) => new URL(icon, SCOPE).href)]);
+  await cache.addAll([...assets]);
+  await cache.put(SCOPE, shell);
+}
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(precache());
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    (async () => {
+      const names = await caches.keys();
+      await Promise.all(
+        names
+          .filter((name) => name.startsWith(PREFIX) && name !== CACHE)
+          .map((name) => caches.delete(name)),
+      );
+      await self.clients.claim();
+    })(),
+  );
+});
+
+// Sent by the app when the user chooses to reload into the new version.
+self.addEventListener("message", (event) => {
+  if (event.data === "skip-waiting") {
+    self.skipWaiting();
+  }
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET" || !request.url.startsWith(SCOPE)) {
+    return;
+  }
+  // Every route of the app is served by the same shell.
+  if (request.mode === "navigate") {
+    event.respondWith(
+      caches.match(SCOPE).then((shell) => shell || fetch(request)),
+    );
+    return;
+  }
+  event.respondWith(
+    caches.open(CACHE).then(async (cache) => {
+      const cached = await cache.match(request);
+      if (cached) {
+        return cached;
+      }
+      const response = await fetch(request);
+      if (response.ok) {
+        cache.put(request, response.clone());
+      }
+      return response;
+    }),
+  );
+});